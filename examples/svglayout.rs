@@ -1,39 +1,66 @@
 extern crate graph_layout;
 extern crate graph_generators;
-extern crate rand;
 
-use rand::{Closed01, random};
 use graph_layout::P2d;
-use graph_layout::svg_writer::{SvgCanvas, SvgWriter};
+use graph_layout::fruchterman_reingold::layout_typical_2d;
+use graph_layout::initial::initial_positions_random;
+use graph_layout::rng::{Rng, XorShiftRng};
+use graph_layout::svg_writer::{EdgeMode, SvgCanvas, SvgWriter};
 use std::fs::File;
 
+/// A random, scale-free graph of `n` nodes after the Barabási–Albert
+/// model: each new node is connected to `m` distinct existing nodes, drawn
+/// with probability proportional to their degree.
+fn barabasi_albert_graph(rng: &mut XorShiftRng, n: usize, m: usize) -> graph_generators::Graph {
+    assert!(n > m && m >= 1);
+    let mut g = graph_generators::Graph::new();
+    let mut targets: Vec<usize> = (0..m).map(|_| g.add_node()).collect();
+    // each node once per incident edge.
+    let mut repeated_nodes = Vec::new();
+    for _ in m..n {
+        let node = g.add_node();
+        for &target in targets.iter() {
+            g.add_edge((node, target));
+            repeated_nodes.push(target);
+            repeated_nodes.push(node);
+        }
+        targets.clear();
+        while targets.len() < m {
+            let i = (rng.next_f32() * repeated_nodes.len() as f32) as usize;
+            let candidate = repeated_nodes[i.min(repeated_nodes.len() - 1)];
+            if !targets.contains(&candidate) {
+                targets.push(candidate);
+            }
+        }
+    }
+    g
+}
+
 fn draw_graph(g: graph_generators::Graph, filename: &str, l: Option<f32>) {
-    let mut node_positions: Vec<P2d> = g.nodes
-                                        .iter()
-                                        .map(|_| {
-                                            P2d(random::<Closed01<f32>>().0,
-                                                random::<Closed01<f32>>().0)
-                                        })
-                                        .collect();
+    let mut rng = XorShiftRng::new(42);
+    let mut node_positions: Vec<P2d> = initial_positions_random(g.nodes.len(), &mut rng);
     let mut node_neighbors: Vec<Vec<usize>> = g.nodes.iter().map(|_| Vec::new()).collect();
     for &(src, dst) in g.edges.iter() {
         node_neighbors[src].push(dst);
     }
 
-    graph_layout::fruchterman_reingold::layout_typical_2d(l, &mut node_positions, &node_neighbors);
+    let outcome = layout_typical_2d(l, &mut node_positions, &node_neighbors, 0).unwrap();
+    if !outcome.converged {
+        println!("{}: not converged after {} iterations", filename, outcome.iterations);
+    }
 
     let mut file = File::create(filename).unwrap();
     let svg_wr = SvgWriter::new(SvgCanvas::default_for_unit_layout(), &mut file);
-    svg_wr.draw_graph(&node_positions, &node_neighbors, false);
+    svg_wr.draw_graph(&node_positions, &node_neighbors, EdgeMode::AsStored).unwrap();
 }
 
 fn main() {
-    let mut rng = rand::thread_rng();
+    let mut rng = XorShiftRng::new(1);
 
-    let g = graph_generators::barabasi_albert_graph(&mut rng, 50, 1);
+    let g = barabasi_albert_graph(&mut rng, 50, 1);
     draw_graph(g, "barabasi_albert_50_1.svg", Some(0.03));
 
-    let g = graph_generators::barabasi_albert_graph(&mut rng, 20, 3);
+    let g = barabasi_albert_graph(&mut rng, 20, 3);
     draw_graph(g, "barabasi_albert_20_3.svg", None);
 
     let mut g = graph_generators::Graph::new();
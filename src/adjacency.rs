@@ -0,0 +1,267 @@
+//!
+//! Helpers operating on adjacency lists (`node_neighbors`), where
+//! `node_neighbors[i]` lists the nodes `i` has an edge to.
+//!
+
+use std::collections::{HashSet, VecDeque};
+use super::GraphLayoutError;
+
+/// Iterates over all edges as `(src, dst)` pairs, in the order they
+/// are stored.
+pub fn edges<'a>(node_neighbors: &'a [Vec<usize>]) -> impl Iterator<Item = (usize, usize)> + 'a {
+    node_neighbors.iter()
+                  .enumerate()
+                  .flat_map(|(src, neighbors)| neighbors.iter().map(move |&dst| (src, dst)))
+}
+
+/// Like `edges`, but treats edges as undirected and yields each pair
+/// of nodes only once, no matter if it is stored in one or both
+/// directions (or multiple times).
+pub fn undirected_edges<'a>(node_neighbors: &'a [Vec<usize>])
+                            -> impl Iterator<Item = (usize, usize)> + 'a {
+    let mut seen = HashSet::new();
+    edges(node_neighbors).filter(move |&(src, dst)| {
+        let key = if src <= dst {
+            (src, dst)
+        } else {
+            (dst, src)
+        };
+        seen.insert(key)
+    })
+}
+
+/// Whether any node lists itself as a neighbor. Such self-loops exert no
+/// force in the layout, and are not drawn.
+pub fn has_self_loops(node_neighbors: &[Vec<usize>]) -> bool {
+    edges(node_neighbors).any(|(src, dst)| src == dst)
+}
+
+/// A dense `num_nodes x num_nodes` matrix, counting the edges from node
+/// `i` to node `j` in entry `[i][j]`. It is symmetric, if each edge is
+/// stored in both directions. Only suitable for small graphs. Returns an
+/// error if the adjacency lists do not `validate`.
+pub fn adjacency_matrix(num_nodes: usize,
+                        node_neighbors: &[Vec<usize>])
+                        -> Result<Vec<Vec<u32>>, GraphLayoutError> {
+    validate(num_nodes, node_neighbors)?;
+    let mut matrix = vec![vec![0; num_nodes]; num_nodes];
+    for (src, dst) in edges(node_neighbors) {
+        matrix[src][dst] += 1;
+    }
+    Ok(matrix)
+}
+
+/// Removes parallel edges, i.e. keeps only the first of multiple
+/// occurrences of a neighbor in each list. Unlike `undirected_edges`, this
+/// keeps edges stored in both directions.
+pub fn collapse_parallel_edges(node_neighbors: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    node_neighbors.iter()
+                  .map(|neighbors| {
+                      let mut seen = HashSet::new();
+                      neighbors.iter().cloned().filter(|&dst| seen.insert(dst)).collect()
+                  })
+                  .collect()
+}
+
+/// Checks that there are adjacency lists for exactly `num_nodes` nodes,
+/// and that all neighbors refer to one of them.
+pub fn validate(num_nodes: usize,
+                node_neighbors: &[Vec<usize>])
+                -> Result<(), GraphLayoutError> {
+    if node_neighbors.len() != num_nodes {
+        return Err(GraphLayoutError::LengthMismatch {
+            expected: num_nodes,
+            actual: node_neighbors.len(),
+        });
+    }
+    match edges(node_neighbors).find(|&(_, dst)| dst >= num_nodes) {
+        Some((node, neighbor)) => {
+            Err(GraphLayoutError::InvalidNeighbor {
+                node,
+                neighbor,
+            })
+        }
+        None => Ok(()),
+    }
+}
+
+// Neighbors of each node in both directions, as if all edges were
+// undirected.
+fn undirected_neighbors(node_neighbors: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut neighbors = vec![Vec::new(); node_neighbors.len()];
+    for (src, dst) in undirected_edges(node_neighbors) {
+        neighbors[src].push(dst);
+        if src != dst {
+            neighbors[dst].push(src);
+        }
+    }
+    neighbors
+}
+
+fn bfs(neighbors: &[Vec<usize>], source: usize) -> Vec<Option<usize>> {
+    let mut distances = vec![None; neighbors.len()];
+    let mut queue = VecDeque::new();
+    distances[source] = Some(0);
+    queue.push_back(source);
+    while let Some(node) = queue.pop_front() {
+        let d = distances[node].unwrap();
+        for &neighbor in neighbors[node].iter() {
+            if distances[neighbor].is_none() {
+                distances[neighbor] = Some(d + 1);
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    distances
+}
+
+/// Number of hops from `source` to every node, treating edges as
+/// undirected. `None` for nodes not reachable from `source`.
+pub fn bfs_distances(node_neighbors: &[Vec<usize>], source: usize) -> Vec<Option<usize>> {
+    assert!(source < node_neighbors.len());
+    bfs(&undirected_neighbors(node_neighbors), source)
+}
+
+/// The edges `(parent, child)` of a breadth-first spanning tree of the
+/// nodes reachable from `root`, treating edges as undirected, in the order
+/// the children are discovered. Spans `n` nodes with `n - 1` edges, which
+/// is all nodes if the graph is connected.
+pub fn spanning_tree(node_neighbors: &[Vec<usize>], root: usize) -> Vec<(usize, usize)> {
+    assert!(root < node_neighbors.len());
+    let neighbors = undirected_neighbors(node_neighbors);
+    let mut visited = vec![false; neighbors.len()];
+    let mut tree = Vec::new();
+    let mut queue = VecDeque::new();
+    visited[root] = true;
+    queue.push_back(root);
+    while let Some(node) = queue.pop_front() {
+        for &neighbor in neighbors[node].iter() {
+            if !visited[neighbor] {
+                visited[neighbor] = true;
+                tree.push((node, neighbor));
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    tree
+}
+
+// Calls `f` with the distances from each node in turn, treating edges as
+// undirected, until `f` returns `false`. Returns whether all nodes were
+// visited.
+pub(crate) fn distances_from_each<F>(node_neighbors: &[Vec<usize>], mut f: F) -> bool
+    where F: FnMut(usize, &[Option<usize>]) -> bool
+{
+    let neighbors = undirected_neighbors(node_neighbors);
+    (0..neighbors.len()).all(|source| f(source, &bfs(&neighbors, source)))
+}
+
+// Calls `f` with the distances from each node in turn. Returns `false`
+// without calling `f` if the graph is empty or disconnected.
+fn all_distances<F>(node_neighbors: &[Vec<usize>], mut f: F) -> bool
+    where F: FnMut(&[Option<usize>])
+{
+    if node_neighbors.is_empty() {
+        return false;
+    }
+    distances_from_each(node_neighbors, |_, distances| {
+        if distances.iter().any(|d| d.is_none()) {
+            return false;
+        }
+        f(distances);
+        true
+    })
+}
+
+/// The longest shortest path (in hops) between any two nodes, treating
+/// edges as undirected. `None` if the graph is empty or disconnected.
+pub fn diameter(node_neighbors: &[Vec<usize>]) -> Option<usize> {
+    let mut max = 0;
+    let connected = all_distances(node_neighbors, |distances| {
+        max = distances.iter().fold(max, |m, d| m.max(d.unwrap()));
+    });
+    if connected { Some(max) } else { None }
+}
+
+/// The mean shortest path length (in hops) over all pairs of distinct
+/// nodes, treating edges as undirected. `None` if the graph has less than
+/// two nodes or is disconnected.
+pub fn average_path_length(node_neighbors: &[Vec<usize>]) -> Option<f32> {
+    let n = node_neighbors.len();
+    if n < 2 {
+        return None;
+    }
+    let mut sum = 0;
+    let connected = all_distances(node_neighbors, |distances| {
+        sum += distances.iter().map(|d| d.unwrap()).sum::<usize>();
+    });
+    if connected {
+        Some(sum as f32 / (n * (n - 1)) as f32)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undirected_edges_of_triangle_stored_both_ways() {
+        let node_neighbors = vec![vec![1, 2], vec![0, 2], vec![0, 1]];
+        assert_eq!(edges(&node_neighbors).count(), 6);
+        let pairs: Vec<_> = undirected_edges(&node_neighbors).collect();
+        assert_eq!(pairs, vec![(0, 1), (0, 2), (1, 2)]);
+    }
+
+    #[test]
+    fn path_of_five_nodes_has_diameter_four() {
+        let path = vec![vec![1], vec![2], vec![3], vec![4], vec![]];
+        assert_eq!(diameter(&path), Some(4));
+        assert_eq!(average_path_length(&path), Some(2.0));
+
+        let disconnected = vec![vec![1], vec![], vec![]];
+        assert_eq!(diameter(&disconnected), None);
+        assert_eq!(average_path_length(&disconnected), None);
+    }
+
+    #[test]
+    fn matrix_of_undirected_triangle_is_symmetric() {
+        let node_neighbors = vec![vec![1, 2], vec![0, 2], vec![0, 1]];
+        let matrix = adjacency_matrix(3, &node_neighbors).unwrap();
+        assert_eq!(matrix, vec![vec![0, 1, 1], vec![1, 0, 1], vec![1, 1, 0]]);
+
+        // parallel edges are counted.
+        let matrix = adjacency_matrix(2, &[vec![1, 1], vec![]]).unwrap();
+        assert_eq!(matrix, vec![vec![0, 2], vec![0, 0]]);
+
+        assert!(adjacency_matrix(2, &node_neighbors).is_err());
+    }
+
+    #[test]
+    fn spanning_tree_of_connected_graph_has_n_minus_one_edges() {
+        // a square with both diagonals, partly stored in both directions,
+        // and a fifth node only reachable against an edge's direction.
+        let node_neighbors = vec![vec![1, 2, 3], vec![0, 2, 3], vec![3], vec![], vec![0]];
+        let tree = spanning_tree(&node_neighbors, 2);
+        assert_eq!(tree.len(), node_neighbors.len() - 1);
+        // every node but the root is reached exactly once.
+        let mut children: Vec<usize> = tree.iter().map(|&(_, child)| child).collect();
+        children.sort();
+        assert_eq!(children, vec![0, 1, 3, 4]);
+        for &(parent, child) in tree.iter() {
+            assert!(node_neighbors[parent].contains(&child) ||
+                    node_neighbors[child].contains(&parent));
+        }
+
+        // on a disconnected graph, it spans the component of the root.
+        assert_eq!(spanning_tree(&[vec![1], vec![], vec![]], 0), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn self_referencing_node_is_detected() {
+        assert!(has_self_loops(&[vec![1], vec![1, 0]]));
+        assert!(!has_self_loops(&[vec![1], vec![0], vec![]]));
+        assert!(!has_self_loops(&[]));
+    }
+}
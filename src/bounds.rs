@@ -0,0 +1,39 @@
+use super::vector::Vector;
+
+/// An axis-aligned box given by its `min` and `max` corners.
+#[derive(Debug, Copy, Clone)]
+pub struct Bounds<V> {
+    pub min: V,
+    pub max: V,
+}
+
+impl<V> Bounds<V>
+    where V: Vector<Scalar = f32>
+{
+    pub fn new(min: V, max: V) -> Bounds<V> {
+        Bounds { min, max }
+    }
+
+    /// The box reaching `half_extent` from `center` in each direction.
+    pub fn from_center_extent(center: V, half_extent: V) -> Bounds<V> {
+        let mut min = center.clone();
+        min.add_scaled(-1.0, &half_extent);
+        let mut max = center;
+        max.add_scaled(1.0, &half_extent);
+        Bounds { min, max }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use P2d;
+
+    #[test]
+    fn from_center_extent_spans_both_directions() {
+        let bounds = Bounds::from_center_extent(P2d(0.0, 0.0), P2d(5.0, 5.0));
+        assert_eq!((bounds.min.0, bounds.min.1), (-5.0, -5.0));
+        assert_eq!((bounds.max.0, bounds.max.1), (5.0, 5.0));
+    }
+}
@@ -0,0 +1,33 @@
+use std::io::{self, Write};
+use super::P2d;
+
+/// Writes one `id,x,y` row per node, preceded by a header row.
+pub fn write_csv(wr: &mut dyn Write, node_positions: &[P2d]) -> io::Result<()> {
+    writeln!(wr, "id,x,y")?;
+    for (i, pos) in node_positions.iter().enumerate() {
+        writeln!(wr, "{},{},{}", i, pos.0, pos.1)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_parse_back_to_the_positions() {
+        let node_positions = [P2d(0.1, 0.2), P2d(-3.5, 1e-7), P2d(1.0 / 3.0, 0.0)];
+        let mut out = Vec::new();
+        write_csv(&mut out, &node_positions).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let mut lines = out.lines();
+        assert_eq!(lines.next(), Some("id,x,y"));
+        let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+        assert_eq!(rows.len(), 3);
+        for (i, (row, pos)) in rows.iter().zip(node_positions.iter()).enumerate() {
+            assert_eq!(row[0].parse::<usize>().unwrap(), i);
+            assert_eq!(row[1].parse::<f32>().unwrap(), pos.0);
+            assert_eq!(row[2].parse::<f32>().unwrap(), pos.1);
+        }
+    }
+}
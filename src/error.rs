@@ -0,0 +1,123 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum GraphLayoutError {
+    /// `node` lists `neighbor` in its adjacency list, but there is no
+    /// such node.
+    InvalidNeighbor { node: usize, neighbor: usize },
+    /// A node index given as argument, e.g. in a subset, is out of range.
+    InvalidNode { node: usize },
+    /// A per-node input, e.g. the adjacency lists, has `actual` entries,
+    /// but there are `expected` nodes.
+    LengthMismatch { expected: usize, actual: usize },
+    /// The named parameter is out of its valid range, e.g. not positive.
+    InvalidParameter(&'static str),
+    /// Writing the output failed.
+    Io(io::Error),
+}
+
+/// Shorthand for `GraphLayoutError`.
+pub type LayoutError = GraphLayoutError;
+
+impl fmt::Display for GraphLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GraphLayoutError::InvalidNeighbor { node, neighbor } => {
+                write!(f, "node {} has neighbor {} which is out of range", node, neighbor)
+            }
+            GraphLayoutError::InvalidNode { node } => write!(f, "node {} is out of range", node),
+            GraphLayoutError::LengthMismatch { expected, actual } => {
+                write!(f, "expected {} entries, one per node, but got {}", expected, actual)
+            }
+            GraphLayoutError::InvalidParameter(name) => write!(f, "invalid parameter {}", name),
+            GraphLayoutError::Io(ref err) => write!(f, "write failed: {}", err),
+        }
+    }
+}
+
+impl Error for GraphLayoutError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            GraphLayoutError::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GraphLayoutError {
+    fn from(err: io::Error) -> GraphLayoutError {
+        GraphLayoutError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+    use P2d;
+    use fruchterman_reingold::{LayoutConfig, layout_subset, layout_typical_2d,
+                               layout_typical_2d_with};
+    use svg_writer::{EdgeMode, SvgCanvas, SvgWriter};
+
+    // A writer which always fails.
+    struct Broken;
+
+    impl Write for Broken {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("broken"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn square() -> Vec<P2d> {
+        vec![P2d(0.1, 0.1), P2d(0.9, 0.1), P2d(0.9, 0.9), P2d(0.1, 0.9)]
+    }
+
+    #[test]
+    fn each_variant_is_returned_for_its_invalid_input() {
+        let node_neighbors = vec![vec![1], vec![2], vec![3], vec![0]];
+
+        match layout_typical_2d(None, &mut square(), &[vec![4], vec![], vec![], vec![]], 0) {
+            Err(LayoutError::InvalidNeighbor { node: 0, neighbor: 4 }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match layout_subset(&LayoutConfig::default(), &mut square(), &node_neighbors, &[1, 7]) {
+            Err(LayoutError::InvalidNode { node: 7 }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match layout_typical_2d(None, &mut square(), &node_neighbors[..3], 0) {
+            Err(LayoutError::LengthMismatch { expected: 4, actual: 3 }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        let config = LayoutConfig { momentum: Some(1.0), ..LayoutConfig::default() };
+        match layout_typical_2d_with(&config, &mut square(), &node_neighbors, 0) {
+            Err(LayoutError::InvalidParameter("momentum")) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        let mut broken = Broken;
+        match SvgWriter::new(SvgCanvas::default_for_unit_layout(), &mut broken)
+            .draw_graph(&square(), &node_neighbors, EdgeMode::AsStored) {
+            Err(ref err @ LayoutError::Io(_)) => assert!(err.source().is_some()),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn messages_name_the_offending_input() {
+        assert_eq!(LayoutError::InvalidNeighbor { node: 1, neighbor: 9 }.to_string(),
+                   "node 1 has neighbor 9 which is out of range");
+        assert_eq!(LayoutError::InvalidNode { node: 9 }.to_string(), "node 9 is out of range");
+        assert_eq!(LayoutError::LengthMismatch { expected: 4, actual: 3 }.to_string(),
+                   "expected 4 entries, one per node, but got 3");
+        assert_eq!(LayoutError::InvalidParameter("padding").to_string(),
+                   "invalid parameter padding");
+        assert_eq!(LayoutError::from(io::Error::other("broken")).to_string(),
+                   "write failed: broken");
+    }
+}
@@ -1,21 +1,39 @@
-///
-/// Implements the force-directed graph layout algorithm as
-/// proposed by Fruchterman and Reingold [1].
-///
-/// [1]: http://emr.cs.iit.edu/~reingold/force-directed.pdf
-///
+//!
+//! Implements the force-directed graph layout algorithm as
+//! proposed by Fruchterman and Reingold [1].
+//!
+//! [1]: http://emr.cs.iit.edu/~reingold/force-directed.pdf
+//!
 
-use super::{P2d, Vector};
+use super::{Bounds, GraphLayoutError, P2d, Vector};
+use super::adjacency;
+use super::geometry::delaunay_edges;
+use super::initial::initial_positions_random;
+use super::json_writer::json_frame;
+use super::metrics::edge_lengths;
+use super::rng::{Rng, XorShiftRng};
+use super::spatial::SpatialGrid;
+use super::transform::isotonic_regression;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::ops::{Deref, DerefMut};
+use std::hash::Hash;
+use std::time::{Duration, Instant};
 
-// k_s == l
+// k_s == l. the magnitude is `d^exponent / k_s`, where FR uses 2.
 #[inline]
-fn attractive_force<V>(p1: &V, p2: &V, k_s: f32) -> V
+fn attractive_force<V>(p1: &V, p2: &V, k_s: f32, exponent: f32) -> V
     where V: Vector<Scalar = f32>
 {
     let force = p1.sub(p2);
     let length = force.length_squared().sqrt();
-    let strength = length / k_s;
-    return force.scale(strength);
+    if length == 0.0 {
+        // `0^(exponent - 1)` is infinite for exponents below 1.
+        return force;
+    }
+    let strength = length.powf(exponent - 1.0) / k_s;
+    force.scale(strength)
 }
 
 // k_r == l^2
@@ -29,9 +47,88 @@ fn repulsive_force<V>(p1: &V, p2: &V, k_r: f32) -> V
         let strength = k_r / length_squared;
         return force.scale(strength);
     }
-    return force;
+    force
+}
+
+// Pushes `p` away from the closest point on the segment `a`-`b`, with a
+// strength of `k` divided by the distance. A point lying exactly on the
+// segment is pushed off perpendicularly.
+#[inline]
+fn node_edge_repulsive_force(p: &P2d, a: &P2d, b: &P2d, k: f32) -> P2d {
+    let ab = b.sub(a);
+    let ab_length_squared = ab.length_squared();
+    if ab_length_squared == 0.0 {
+        return repulsive_force(p, a, k);
+    }
+    let ap = p.sub(a);
+    let t = ((ap.0 * ab.0 + ap.1 * ab.1) / ab_length_squared).clamp(0.0, 1.0);
+    let mut closest = *a;
+    closest.add_scaled(t, &ab);
+
+    let force = p.sub(&closest);
+    let length_squared = force.length_squared();
+    if length_squared > 0.0 {
+        return force.scale(k / length_squared);
+    }
+    // treat it as if it was very close.
+    let ab_length = ab_length_squared.sqrt();
+    P2d(-ab.1, ab.0).scale(k / (ab_length * 0.01 * ab_length))
+}
+
+// Potential of a single spring, whose negative gradient is `attractive_force`.
+#[inline]
+fn attractive_energy<V>(p1: &V, p2: &V, k_s: f32, exponent: f32) -> f32
+    where V: Vector<Scalar = f32>
+{
+    let length = p1.distance_squared_to_point(p2).sqrt();
+    length.powf(exponent + 1.0) / ((exponent + 1.0) * k_s)
+}
+
+// Potential between two nodes, whose negative gradient is `repulsive_force`.
+#[inline]
+fn repulsive_energy<V>(p1: &V, p2: &V, k_r: f32) -> f32
+    where V: Vector<Scalar = f32>
+{
+    let length_squared = p1.distance_squared_to_point(p2);
+    if length_squared > 0.0 {
+        return -0.5 * k_r * length_squared.ln();
+    }
+    0.0
+}
+
+/// The energy of a layout, i.e. the potential whose negative gradient
+/// are the forces applied during layout. Lower is better.
+pub fn energy<V>(node_positions: &[V], node_neighbors: &[Vec<usize>], k_r: f32, k_s: f32) -> f32
+    where V: Vector<Scalar = f32>
+{
+    energy_with_exponent(node_positions, node_neighbors, k_r, k_s, DEFAULT_SPRING_EXPONENT)
+}
+
+// Like `energy`, but for springs with the given `exponent`.
+fn energy_with_exponent<V>(node_positions: &[V],
+                           node_neighbors: &[Vec<usize>],
+                           k_r: f32,
+                           k_s: f32,
+                           exponent: f32)
+                           -> f32
+    where V: Vector<Scalar = f32>
+{
+    let n = node_positions.len();
+    let mut sum = 0.0;
+    for i1 in 0..n {
+        for i2 in i1 + 1..n {
+            sum += repulsive_energy(&node_positions[i1], &node_positions[i2], k_r);
+        }
+        for &i2 in node_neighbors[i1].iter() {
+            sum += attractive_energy(&node_positions[i1], &node_positions[i2], k_s, exponent);
+        }
+    }
+    sum
 }
 
+/// Implementations should visit node pairs and edges in a fixed order,
+/// so that floating point sums, and with them the layout, are bit-for-bit
+/// reproducible for identical input.
 pub trait ForceDirected<V> where V: Vector<Scalar = f32>
 {
     fn reset_forces(&mut self);
@@ -39,9 +136,16 @@ pub trait ForceDirected<V> where V: Vector<Scalar = f32>
     // adds result of `f` to force1 and substracts from force2
     fn update_force_each_node_pair<F: Fn(&V, &V) -> V>(&mut self, f: F);
 
-    // adds result of `f` to force1 and substracts from force2
+    // adds result of `f` to force1 and substracts from force2. an edge
+    // stored multiple times is visited multiple times, so parallel edges
+    // multiply the attraction.
     fn update_force_each_edge<F: Fn(&V, &V) -> V>(&mut self, f: F);
 
+    // adds result of `f(node, edge_start, edge_end)` to the force of each
+    // node, for each edge not incident to it. does nothing by default, i.e.
+    // implementations without support ignore node-edge repulsion.
+    fn update_force_each_node_edge<F: Fn(&V, &V, &V) -> V>(&mut self, _f: F) {}
+
     fn update_positions<F: FnMut(&V, &V) -> V>(&mut self, f: F);
 }
 
@@ -57,9 +161,62 @@ fn iterate<V, FD>(fd: &mut FD, step: f32, k_r: f32, k_s: f32, min_pos: &V, max_p
     fd.update_force_each_node_pair(|pos1, pos2| repulsive_force(pos1, pos2, k_r));
 
     // Calculate spring force between adjacent pairs (edges).
-    fd.update_force_each_edge(|pos1, pos2| attractive_force(pos1, pos2, k_s).scale(-1.0));
+    fd.update_force_each_edge(|pos1, pos2| {
+        attractive_force(pos1, pos2, k_s, DEFAULT_SPRING_EXPONENT).scale(-1.0)
+    });
+
+    move_positions(fd, step, min_pos, max_pos)
+}
+
+/// Time spent in each phase of an iteration.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct PhaseTimings {
+    pub repulsion: Duration,
+    pub attraction: Duration,
+    pub update: Duration,
+}
+
+/// Performs a single iteration like `Layout::step`, but measures the
+/// time spent in each phase. Returns the distance moved and the timings.
+pub fn iterate_timed<V, FD>(fd: &mut FD,
+                            step: f32,
+                            k_r: f32,
+                            k_s: f32,
+                            min_pos: &V,
+                            max_pos: &V)
+                            -> (f32, PhaseTimings)
+    where V: Vector<Scalar = f32>,
+          FD: ForceDirected<V>
+{
+    let start = Instant::now();
+    fd.reset_forces();
+    fd.update_force_each_node_pair(|pos1, pos2| repulsive_force(pos1, pos2, k_r));
+    let repulsion = start.elapsed();
+
+    let start = Instant::now();
+    fd.update_force_each_edge(|pos1, pos2| {
+        attractive_force(pos1, pos2, k_s, DEFAULT_SPRING_EXPONENT).scale(-1.0)
+    });
+    let attraction = start.elapsed();
+
+    let start = Instant::now();
+    let sum_distance = move_positions(fd, step, min_pos, max_pos);
+    let update = start.elapsed();
 
-    // update positions
+    (sum_distance,
+     PhaseTimings {
+        repulsion,
+        attraction,
+        update,
+    })
+}
+
+// Moves each node by `step` in the direction of its force. Returns the
+// total distance moved.
+fn move_positions<V, FD>(fd: &mut FD, step: f32, min_pos: &V, max_pos: &V) -> f32
+    where V: Vector<Scalar = f32>,
+          FD: ForceDirected<V>
+{
     let mut sum_distance = 0.0;
 
     fd.update_positions(|position, force| {
@@ -67,7 +224,7 @@ fn iterate<V, FD>(fd: &mut FD, step: f32, k_r: f32, k_s: f32, min_pos: &V, max_p
 
         let length = force.length_squared().sqrt();
         if length > 0.0 {
-            new_pos.add_scaled(step / length, &force);
+            new_pos.add_scaled(step / length, force);
 
             // add up the moved distance. we move by step.
             sum_distance += step;
@@ -75,9 +232,12 @@ fn iterate<V, FD>(fd: &mut FD, step: f32, k_r: f32, k_s: f32, min_pos: &V, max_p
         new_pos.clip_within(min_pos, max_pos)
     });
 
-    return sum_distance;
+    sum_distance
 }
 
+/// Runs at most `max_iter` iterations and returns the number of
+/// iterations actually performed.
+#[allow(clippy::too_many_arguments)]
 pub fn layout<V, FD, F>(fd: &mut FD,
                         step_fn: F,
                         max_iter: usize,
@@ -86,6 +246,7 @@ pub fn layout<V, FD, F>(fd: &mut FD,
                         k_s: f32,
                         min_pos: &V,
                         max_pos: &V)
+                        -> usize
     where V: Vector<Scalar = f32>,
           FD: ForceDirected<V>,
           F: Fn(usize) -> f32
@@ -100,112 +261,2727 @@ pub fn layout<V, FD, F>(fd: &mut FD,
             break;
         }
     }
+    iter
+}
+
+/// Stop updating nodes which are close to their equilibrium.
+///
+/// As every node moves by the full step size, no matter how strong the
+/// force on it is, the magnitude of the force measures how far a node is
+/// from its equilibrium. A node settles, once its force stays below
+/// `threshold` for `iterations` consecutive iterations. Settled nodes are
+/// not moved, and the repulsion between two settled nodes is not computed.
+/// A settled node is woken up again when one of its neighbors is not
+/// settled and feels a force of at least `threshold`.
+#[derive(Debug, Copy, Clone)]
+pub struct Freeze {
+    pub threshold: f32,
+    pub iterations: usize,
+}
+
+// The forces of a `Layout`, either allocated by it, or provided by the
+// caller of `Layout::with_forces`.
+enum ForceBuffer<'a, V: 'a> {
+    Owned(Vec<V>),
+    Borrowed(&'a mut [V]),
+}
+
+impl<'a, V> Deref for ForceBuffer<'a, V> {
+    type Target = [V];
+
+    fn deref(&self) -> &[V] {
+        match *self {
+            ForceBuffer::Owned(ref forces) => forces,
+            ForceBuffer::Borrowed(ref forces) => forces,
+        }
+    }
+}
+
+impl<'a, V> DerefMut for ForceBuffer<'a, V> {
+    fn deref_mut(&mut self) -> &mut [V] {
+        match *self {
+            ForceBuffer::Owned(ref mut forces) => forces,
+            ForceBuffer::Borrowed(ref mut forces) => forces,
+        }
+    }
 }
 
-struct Layout<'a, 'b, V: 'a> {
-    forces: Vec<V>,
+/// The state of a layout in progress, which can be driven by `layout`,
+/// or incrementally by `step`.
+pub struct Layout<'a, 'b, V: 'a> {
+    // force of each active node, indexed by its slot.
+    forces: ForceBuffer<'a, V>,
+    // the nodes which are moved, by slot. `None` if all nodes are, in which
+    // case the slot of a node is its index.
+    active: Option<Vec<usize>>,
+    // the slot of each active node. empty if all nodes are active.
+    slots: HashMap<usize, usize>,
     node_positions: &'a mut Vec<V>,
     node_neighbors: &'b [Vec<usize>],
     lock_first_n_positions: usize,
+    // whether each active node is held at its position, by slot. empty if
+    // none was ever pinned.
+    pinned: Vec<bool>,
+    // factor of each node, by which the repulsion between two nodes is
+    // scaled. empty if unweighted.
+    repulsion_weights: Vec<f32>,
+    freeze: Option<Freeze>,
+    // for each active node, by slot, the number of consecutive iterations
+    // its force stayed below the freeze threshold.
+    calm_iterations: Vec<usize>,
+    // for each active node, by slot, the nodes it shares an edge with, in
+    // either direction.
+    incident: Vec<Vec<usize>>,
+    // whether each active node, by slot, is not settled and felt a force
+    // above the freeze threshold. only used by `update_settled`.
+    restless: Vec<bool>,
+    // whether positions were updated since the forces were last reset.
+    positions_updated: bool,
+    // applied to the force of each node before moving it, to restrict the
+    // directions nodes can move in.
+    force_filter: Option<fn(&V) -> V>,
+    // bounds of individual active nodes, by slot. empty if there are none.
+    node_bounds: Vec<Option<Bounds<V>>>,
+    // damping factor of the velocities, if nodes move with momentum.
+    damping: Option<f32>,
+    // velocity of each active node, by slot. empty without momentum.
+    velocities: Vec<V>,
+    // sum of the squared displacements of the last update.
+    total_velocity: f32,
+    // position each active node is pulled towards, with the strength of
+    // the pull, by slot. empty if there are none.
+    targets: Vec<Option<(V, f32)>>,
+    // pairs of nodes adjacent in the Delaunay triangulation of the
+    // positions, as of the last call of `triangulate`.
+    delaunay_pairs: Vec<(usize, usize)>,
+}
+
+impl<'a, 'b> Layout<'a, 'b, P2d> {
+    // Enables the node-level options of `config`, after checking it.
+    fn configure(&mut self, config: &LayoutConfig) -> Result<(), GraphLayoutError> {
+        validate_config(config, self.node_positions.len())?;
+        if let Some(freeze) = config.freeze {
+            self.freeze(freeze);
+        }
+        if config.degree_weighted_repulsion {
+            self.scale_repulsion_by_degree();
+        }
+        for (i, bounds) in config.node_bounds.iter().enumerate() {
+            if let Some(bounds) = *bounds {
+                self.set_node_bounds(i, bounds);
+            }
+        }
+        if let Some(damping) = config.momentum {
+            self.momentum(damping);
+        }
+        for (i, target) in config.targets.iter().enumerate() {
+            if let Some((target, strength)) = *target {
+                self.set_target(i, target, strength);
+            }
+        }
+        if let Some(height) = config.isolated_strip {
+            self.tidy_isolated_nodes(height);
+        }
+        Ok(())
+    }
+
+    /// Pulls the movable nodes towards the closest x-coordinates, which
+    /// follow the order of `ranks`, indexed by node, by `strength` times the
+    /// distance. Nodes of lower rank are then left of nodes of higher rank,
+    /// e.g. for timelines with timestamps as ranks. Nodes of equal rank may
+    /// be in any order.
+    pub fn order_by_rank(&mut self, ranks: &[f32], strength: f32) {
+        assert_eq!(ranks.len(), self.node_positions.len());
+        let mut order: Vec<usize> = (0..ranks.len()).collect();
+        order.sort_by(|&i, &j| {
+            (ranks[i], self.node_positions[i].0)
+                .partial_cmp(&(ranks[j], self.node_positions[j].0))
+                .unwrap_or(Ordering::Equal)
+        });
+        let xs: Vec<f32> = order.iter().map(|&i| self.node_positions[i].0).collect();
+        let ordered = isotonic_regression(&xs, &vec![1.0; xs.len()]);
+        for (&i, &x) in order.iter().zip(ordered.iter()) {
+            if self.is_movable(i) {
+                self.node_positions[i].0 += strength * (x - self.node_positions[i].0);
+            }
+        }
+    }
+
+    /// Projects the movable nodes outside of the disk around `center` with
+    /// `radius` onto its boundary.
+    pub fn clip_to_disk(&mut self, center: &P2d, radius: f32) {
+        for i in 0..self.node_positions.len() {
+            if self.is_movable(i) {
+                self.node_positions[i] = self.node_positions[i].clip_within_disk(center, radius);
+            }
+        }
+    }
+
+    // The fraction of the movable nodes, which lie on the boundary of the
+    // box given by `min_pos` and `max_pos`.
+    fn clamped_fraction(&self, min_pos: &P2d, max_pos: &P2d) -> f32 {
+        let (mut movable, mut clamped) = (0, 0);
+        for (i, pos) in self.node_positions.iter().enumerate() {
+            if !self.is_movable(i) {
+                continue;
+            }
+            movable += 1;
+            if pos.0 <= min_pos.0 || pos.0 >= max_pos.0 || pos.1 <= min_pos.1 ||
+               pos.1 >= max_pos.1 {
+                clamped += 1;
+            }
+        }
+        if movable == 0 { 0.0 } else { clamped as f32 / movable as f32 }
+    }
+
+    /// Reserves a strip of `height` along the `y = 1` edge of the unit
+    /// square for the movable nodes without edges (apart from self-loops),
+    /// which have nothing to hold them and are otherwise pushed into the
+    /// corners. Pins them to a grid within the strip, and keeps the other
+    /// movable nodes without bounds of their own out of it.
+    pub fn tidy_isolated_nodes(&mut self, height: f32) {
+        assert!(height > 0.0 && height < 1.0);
+        let n = self.node_positions.len();
+        let mut connected = vec![false; n];
+        for (src, dst) in adjacency::edges(self.node_neighbors) {
+            if src != dst {
+                connected[src] = true;
+                connected[dst] = true;
+            }
+        }
+        let isolated: Vec<usize> =
+            (0..n).filter(|&i| !connected[i] && self.is_movable(i)).collect();
+        if isolated.is_empty() {
+            return;
+        }
+
+        // a grid of about square cells filling the strip.
+        let k = isolated.len();
+        let cols = ((k as f32 / height).sqrt().ceil() as usize).min(k);
+        let rows = k.div_ceil(cols);
+        let (cell_width, cell_height) = (1.0 / cols as f32, height / rows as f32);
+        for (j, &i) in isolated.iter().enumerate() {
+            let (row, col) = (j / cols, j % cols);
+            self.pin(i,
+                     P2d((col as f32 + 0.5) * cell_width,
+                         1.0 - height + (row as f32 + 0.5) * cell_height));
+        }
+
+        let above = Bounds::new(P2d(0.0, 0.0), P2d(1.0, 1.0 - height));
+        let unbounded: Vec<usize> =
+            (0..n).filter(|&i| {
+                      connected[i] && self.is_movable(i) &&
+                      self.own_bounds(i).is_none()
+                  })
+                  .collect();
+        for i in unbounded {
+            self.set_node_bounds(i, above);
+        }
+    }
+
+    /// Like `update_force_each_node_pair`, but only visits pairs of nodes
+    /// closer than `cutoff`, which are looked up in a `SpatialGrid`. For
+    /// evenly spread nodes, this takes linear instead of quadratic time.
+    pub fn update_force_each_node_pair_within<F>(&mut self, cutoff: f32, f: F)
+        where F: Fn(&P2d, &P2d) -> P2d
+    {
+        let grid = SpatialGrid::from_positions(cutoff, self.node_positions);
+        let cutoff_squared = cutoff * cutoff;
+        for s1 in 0..self.forces.len() {
+            let i1 = self.node_at(s1);
+            let settled = self.is_settled(i1);
+            for i2 in grid.neighbors_within(grid.cell_of(&self.node_positions[i1])) {
+                let s2 = self.slot(i2);
+                if s2.is_some_and(|s2| s2 <= s1) || settled && self.is_settled(i2) {
+                    continue;
+                }
+                let (pos1, pos2) = (&self.node_positions[i1], &self.node_positions[i2]);
+                if pos1.distance_squared_to_point(pos2) >= cutoff_squared {
+                    continue;
+                }
+                let force = f(pos1, pos2);
+                self.add_pair_force(s1, i1, s2, i2, force);
+            }
+        }
+    }
+
+    /// Computes the Delaunay triangulation of the current positions, which
+    /// `update_force_each_delaunay_pair` uses until the next call.
+    pub fn triangulate(&mut self) {
+        self.delaunay_pairs = delaunay_edges(self.node_positions);
+    }
+
+    /// Like `update_force_each_node_pair`, but only visits pairs of nodes
+    /// which are adjacent in the triangulation computed by the last call of
+    /// `triangulate`. This takes linear time, and still keeps each node
+    /// apart from its nearest neighbors.
+    pub fn update_force_each_delaunay_pair<F>(&mut self, f: F)
+        where F: Fn(&P2d, &P2d) -> P2d
+    {
+        for k in 0..self.delaunay_pairs.len() {
+            // the first node of the pair needs to be active.
+            let (i1, i2) = match self.delaunay_pairs[k] {
+                (i1, i2) if self.slot(i1).is_none() => (i2, i1),
+                pair => pair,
+            };
+            let s1 = match self.slot(i1) {
+                Some(s1) => s1,
+                None => continue,
+            };
+            let s2 = self.slot(i2);
+            if self.is_settled(i1) && self.is_settled(i2) {
+                continue;
+            }
+            let force = f(&self.node_positions[i1], &self.node_positions[i2]);
+            self.add_pair_force(s1, i1, s2, i2, force);
+        }
+    }
 }
 
 impl<'a, 'b, V> Layout<'a, 'b, V>
     where V: Vector<Scalar = f32>
 {
-    fn new<'c, 'd>(node_positions: &'c mut Vec<V>,
-                   node_neighbors: &'d [Vec<usize>])
-                   -> Layout<'c, 'd, V> {
-        let n = node_positions.len();
-        assert!(node_neighbors.len() == n);
+    pub fn new<'c, 'd>(node_positions: &'c mut Vec<V>,
+                       node_neighbors: &'d [Vec<usize>])
+                       -> Result<Layout<'c, 'd, V>, GraphLayoutError> {
+        adjacency::validate(node_positions.len(), node_neighbors)?;
+        Ok(Layout::with_active(node_positions, node_neighbors, None, HashMap::new()))
+    }
+
+    /// Like `new`, but keeps the forces in `forces`, one per node, instead
+    /// of allocating them. Neither this nor stepping the layout allocates,
+    /// unless options are enabled which keep per-node state.
+    ///
+    /// # Panics
+    ///
+    /// If the length of `forces` differs from the number of nodes.
+    pub fn with_forces<'c, 'd>(node_positions: &'c mut Vec<V>,
+                               node_neighbors: &'d [Vec<usize>],
+                               forces: &'c mut [V])
+                               -> Result<Layout<'c, 'd, V>, GraphLayoutError> {
+        assert!(forces.len() == node_positions.len());
+        adjacency::validate(node_positions.len(), node_neighbors)?;
+        for force in forces.iter_mut() {
+            *force = V::zero();
+        }
+        Ok(Layout::with_buffer(node_positions,
+                               node_neighbors,
+                               None,
+                               HashMap::new(),
+                               ForceBuffer::Borrowed(forces)))
+    }
+
+    fn with_active<'c, 'd>(node_positions: &'c mut Vec<V>,
+                           node_neighbors: &'d [Vec<usize>],
+                           active: Option<Vec<usize>>,
+                           slots: HashMap<usize, usize>)
+                           -> Layout<'c, 'd, V> {
+        let num_forces = match active {
+            Some(ref active) => active.len(),
+            None => node_positions.len(),
+        };
+        let forces = (0..num_forces).map(|_| V::zero()).collect(); // initialize forces
+        Layout::with_buffer(node_positions,
+                            node_neighbors,
+                            active,
+                            slots,
+                            ForceBuffer::Owned(forces))
+    }
+
+    fn with_buffer<'c, 'd>(node_positions: &'c mut Vec<V>,
+                           node_neighbors: &'d [Vec<usize>],
+                           active: Option<Vec<usize>>,
+                           slots: HashMap<usize, usize>,
+                           forces: ForceBuffer<'c, V>)
+                           -> Layout<'c, 'd, V> {
         Layout {
-            forces: (0..n).map(|_| V::new()).collect(), // initialize forces
-            node_positions: node_positions,
-            node_neighbors: node_neighbors,
+            forces,
+            active,
+            slots,
+            node_positions,
+            node_neighbors,
             lock_first_n_positions: 0,
+            pinned: Vec::new(),
+            repulsion_weights: Vec::new(),
+            freeze: None,
+            calm_iterations: Vec::new(),
+            incident: Vec::new(),
+            restless: Vec::new(),
+            positions_updated: false,
+            force_filter: None,
+            node_bounds: Vec::new(),
+            damping: None,
+            velocities: Vec::new(),
+            total_velocity: 0.0,
+            targets: Vec::new(),
+            delaunay_pairs: Vec::new(),
+        }
+    }
+
+    /// Like `new`, but only the nodes in `subset` are moved. All other
+    /// nodes stay where they are, but still exert forces. Memory for the
+    /// forces and other per-node state is only allocated for the nodes in
+    /// `subset`, and only pairs of nodes involving one of them are visited.
+    pub fn new_subset<'c, 'd>(node_positions: &'c mut Vec<V>,
+                              node_neighbors: &'d [Vec<usize>],
+                              subset: &[usize])
+                              -> Result<Layout<'c, 'd, V>, GraphLayoutError> {
+        let n = node_positions.len();
+        adjacency::validate(n, node_neighbors)?;
+        let mut active = Vec::with_capacity(subset.len());
+        let mut slots = HashMap::with_capacity(subset.len());
+        for &i in subset.iter() {
+            if i >= n {
+                return Err(GraphLayoutError::InvalidNode { node: i });
+            }
+            if let Entry::Vacant(slot) = slots.entry(i) {
+                slot.insert(active.len());
+                active.push(i);
+            }
+        }
+        Ok(Layout::with_active(node_positions, node_neighbors, Some(active), slots))
+    }
+
+    // The slot of node `i` in `forces`, if it is active.
+    fn slot(&self, i: usize) -> Option<usize> {
+        match self.active {
+            Some(_) => self.slots.get(&i).cloned(),
+            None => Some(i),
+        }
+    }
+
+    // Adds `force` between nodes `i1` and `i2` to their forces, scaled by
+    // their repulsion weights.
+    fn add_pair_force(&mut self, s1: usize, i1: usize, s2: Option<usize>, i2: usize, force: V) {
+        let force = if self.repulsion_weights.is_empty() {
+            force
+        } else {
+            force.scale(self.repulsion_weights[i1] * self.repulsion_weights[i2])
+        };
+        self.forces[s1].add_scaled(1.0, &force);
+        if let Some(s2) = s2 {
+            self.forces[s2].add_scaled(-1.0, &force);
+        }
+    }
+
+    // The node of slot `s` in `forces`.
+    fn node_at(&self, s: usize) -> usize {
+        match self.active {
+            Some(ref active) => active[s],
+            None => s,
         }
     }
 
-    fn lock_first_n_positions(&mut self, n: usize) {
+    pub fn lock_first_n_positions(&mut self, n: usize) {
         self.lock_first_n_positions = n;
     }
+
+    pub fn positions(&self) -> &[V] {
+        self.node_positions
+    }
+
+    // The number of active nodes, i.e. of slots.
+    fn num_slots(&self) -> usize {
+        self.forces.len()
+    }
+
+    /// Moves node `index` to `pos` and holds it there, until it is unpinned.
+    pub fn pin(&mut self, index: usize, pos: V) {
+        self.node_positions[index] = pos;
+        // nodes which are not active are held anyway.
+        if let Some(s) = self.slot(index) {
+            if self.pinned.is_empty() {
+                self.pinned = vec![false; self.num_slots()];
+            }
+            self.pinned[s] = true;
+        }
+    }
+
+    pub fn unpin(&mut self, index: usize) {
+        if let Some(s) = self.slot(index) {
+            if !self.pinned.is_empty() {
+                self.pinned[s] = false;
+            }
+        }
+    }
+
+    pub fn is_pinned(&self, index: usize) -> bool {
+        !self.pinned.is_empty() && self.slot(index).is_some_and(|s| self.pinned[s])
+    }
+
+    // The bounds of node `i` of its own, if any.
+    fn own_bounds(&self, i: usize) -> Option<&Bounds<V>> {
+        match self.slot(i) {
+            Some(s) if !self.node_bounds.is_empty() => self.node_bounds[s].as_ref(),
+            _ => None,
+        }
+    }
+
+    // Whether `update_positions` moves node `i`.
+    fn is_movable(&self, i: usize) -> bool {
+        i >= self.lock_first_n_positions && self.slot(i).is_some() && !self.is_settled(i) &&
+        !self.is_pinned(i)
+    }
+
+    /// Translates the movable nodes, so that their centroid is at `target`.
+    /// Nodes are kept within `min_pos` and `max_pos`, and their own bounds,
+    /// which can leave the centroid short of `target`.
+    pub fn recenter(&mut self, target: &V, min_pos: &V, max_pos: &V) {
+        let movable: Vec<usize> = (0..self.node_positions.len())
+                                      .filter(|&i| self.is_movable(i))
+                                      .collect();
+        if movable.is_empty() {
+            return;
+        }
+
+        let mut sum = V::zero();
+        for &i in movable.iter() {
+            sum.add_scaled(1.0, &self.node_positions[i]);
+        }
+        let mut offset = target.sub(&sum.scale(1.0 / movable.len() as f32));
+        if let Some(filter) = self.force_filter {
+            offset = filter(&offset);
+        }
+        for &i in movable.iter() {
+            let mut pos = self.node_positions[i].clone();
+            pos.add_scaled(1.0, &offset);
+            pos = pos.clip_within(min_pos, max_pos);
+            if let Some(bounds) = self.own_bounds(i) {
+                pos = pos.clip_within(&bounds.min, &bounds.max);
+            }
+            self.node_positions[i] = pos;
+        }
+    }
+
+    /// Scales the repulsion between nodes `i` and `j` by
+    /// `(deg_i + 1) * (deg_j + 1)`, as in ForceAtlas2, so that hubs get
+    /// more room. Edges count towards the degree of both of their nodes,
+    /// once, even if stored in both directions. Self-loops do not count.
+    pub fn scale_repulsion_by_degree(&mut self) {
+        let mut weights = vec![1.0; self.node_positions.len()];
+        for (i1, i2) in adjacency::undirected_edges(self.node_neighbors) {
+            if i1 != i2 {
+                weights[i1] += 1.0;
+                weights[i2] += 1.0;
+            }
+        }
+        self.repulsion_weights = weights;
+    }
+
+    /// Keeps node `index` within `bounds`, in addition to the bounds
+    /// applying to all nodes.
+    pub fn set_node_bounds(&mut self, index: usize, bounds: Bounds<V>) {
+        // nodes which are not active do not move anyway.
+        if let Some(s) = self.slot(index) {
+            if self.node_bounds.is_empty() {
+                self.node_bounds = vec![None; self.num_slots()];
+            }
+            self.node_bounds[s] = Some(bounds);
+        }
+    }
+
+    /// Pulls node `index` towards `target` by a spring of the given
+    /// `strength`, i.e. by a force of `strength` times the distance to
+    /// `target`. Unlike `pin`, the node can still give way to other forces.
+    pub fn set_target(&mut self, index: usize, target: V, strength: f32) {
+        if let Some(s) = self.slot(index) {
+            if self.targets.is_empty() {
+                self.targets = vec![None; self.num_slots()];
+            }
+            self.targets[s] = Some((target, strength));
+        }
+    }
+
+    /// Gives the nodes inertia: each node carries a velocity, which is
+    /// multiplied by `damping` and then accelerated by `1 - damping` times
+    /// the direction of the force in every iteration. Nodes move in the
+    /// direction of their velocity instead of their force, by the step
+    /// times the magnitude of their velocity, which is at most 1. So a node
+    /// pushed the same way over several iterations picks up the full step,
+    /// while a node whose force flips back and forth slows down.
+    pub fn momentum(&mut self, damping: f32) {
+        assert!((0.0..1.0).contains(&damping));
+        self.damping = Some(damping);
+        self.velocities = (0..self.num_slots()).map(|_| V::zero()).collect();
+    }
+
+    /// The sum of the squared distances the nodes moved in the last
+    /// iteration, i.e. their kinetic energy. It decreases smoothly as the
+    /// layout settles, which makes it suitable to display progress.
+    pub fn total_velocity(&self) -> f32 {
+        self.total_velocity
+    }
+
+    /// The force on each node, as computed in the last iteration. Zero for
+    /// nodes which are not moved.
+    pub fn node_forces(&self) -> Vec<V> {
+        (0..self.node_positions.len())
+            .map(|i| match self.slot(i) {
+                Some(s) => self.forces[s].clone(),
+                None => V::zero(),
+            })
+            .collect()
+    }
+
+    /// Performs a single iteration. Returns the distance moved.
+    pub fn step(&mut self, step: f32, k_r: f32, k_s: f32, min_pos: &V, max_pos: &V) -> f32 {
+        iterate(self, step, k_r, k_s, min_pos, max_pos)
+    }
+
+    pub fn freeze(&mut self, freeze: Freeze) {
+        let mut incident: Vec<Vec<usize>> = (0..self.num_slots()).map(|_| Vec::new()).collect();
+        for (i1, i2) in adjacency::edges(self.node_neighbors).filter(|&(i1, i2)| i1 != i2) {
+            if let Some(s1) = self.slot(i1) {
+                incident[s1].push(i2);
+            }
+            if let Some(s2) = self.slot(i2) {
+                incident[s2].push(i1);
+            }
+        }
+        self.freeze = Some(freeze);
+        self.calm_iterations = vec![0; self.num_slots()];
+        self.restless = vec![false; self.num_slots()];
+        self.incident = incident;
+    }
+
+    // Nodes which are not active never move, so they count as settled.
+    fn is_settled(&self, i: usize) -> bool {
+        match self.freeze {
+            Some(freeze) => {
+                self.slot(i).is_none_or(|s| self.calm_iterations[s] >= freeze.iterations)
+            }
+            None => false,
+        }
+    }
+
+    fn update_settled(&mut self) {
+        let freeze = match self.freeze {
+            Some(freeze) => freeze,
+            None => return,
+        };
+        let threshold_squared = freeze.threshold * freeze.threshold;
+
+        for s in 0..self.num_slots() {
+            self.restless[s] = self.calm_iterations[s] < freeze.iterations &&
+                               self.forces[s].length_squared() >= threshold_squared;
+        }
+
+        for s in 0..self.num_slots() {
+            if self.calm_iterations[s] >= freeze.iterations {
+                let woken = self.incident[s]
+                                .iter()
+                                .any(|&j| self.slot(j).is_some_and(|t| self.restless[t]));
+                if woken {
+                    self.calm_iterations[s] = 0;
+                }
+            } else if self.restless[s] {
+                self.calm_iterations[s] = 0;
+            } else {
+                self.calm_iterations[s] += 1;
+            }
+        }
+    }
 }
 
 impl<'a, 'b, V> ForceDirected<V> for Layout<'a, 'b, V>
     where V: Vector<Scalar = f32>
 {
     fn reset_forces(&mut self) {
+        // judge the forces of the previous iteration, before they are gone.
+        if self.positions_updated {
+            self.update_settled();
+            self.positions_updated = false;
+        }
         for f in self.forces.iter_mut() {
             f.reset();
         }
     }
 
+    // Visits pairs `(i1, i2)` with `i1 < i2` in lexicographic order, which
+    // makes the summation order, and thus the result, deterministic.
+    // With a subset of active nodes, visits for each active node in turn
+    // the pairs with all nodes which were not visited before.
     fn update_force_each_node_pair<F: Fn(&V, &V) -> V>(&mut self, f: F) {
         let n = self.node_positions.len();
+        if self.active.is_some() {
+            for s1 in 0..self.forces.len() {
+                let i1 = self.node_at(s1);
+                let settled = self.is_settled(i1);
+                for i2 in 0..n {
+                    let s2 = self.slot(i2);
+                    if s2.is_some_and(|s2| s2 <= s1) || settled && self.is_settled(i2) {
+                        continue;
+                    }
+                    let force = f(&self.node_positions[i1], &self.node_positions[i2]);
+                    self.add_pair_force(s1, i1, s2, i2, force);
+                }
+            }
+            return;
+        }
         assert!(n == self.forces.len());
 
-        for i1 in 0..n - 1 {
+        for i1 in 0..n {
+            let settled = self.is_settled(i1);
             for i2 in i1 + 1..n {
+                if settled && self.is_settled(i2) {
+                    continue;
+                }
                 let force = f(&self.node_positions[i1], &self.node_positions[i2]);
-                self.forces[i1].add_scaled(1.0, &force);
-                self.forces[i2].add_scaled(-1.0, &force);
+                self.add_pair_force(i1, i1, Some(i2), i2, force);
             }
         }
     }
 
     fn update_force_each_edge<F: Fn(&V, &V) -> V>(&mut self, f: F) {
         let n = self.node_positions.len();
-        assert!(n == self.forces.len());
 
         for i1 in 0..n {
-            for &i2 in self.node_neighbors[i1].iter() {
+            // self-loops have zero length, and thus no effect.
+            for &i2 in self.node_neighbors[i1].iter().filter(|&&i2| i2 != i1) {
+                let (s1, s2) = (self.slot(i1), self.slot(i2));
+                if s1.is_none() && s2.is_none() {
+                    continue;
+                }
                 let force = f(&self.node_positions[i1], &self.node_positions[i2]);
-                self.forces[i1].add_scaled(1.0, &force);
-                self.forces[i2].add_scaled(-1.0, &force);
+                if let Some(s1) = s1 {
+                    self.forces[s1].add_scaled(1.0, &force);
+                }
+                if let Some(s2) = s2 {
+                    self.forces[s2].add_scaled(-1.0, &force);
+                }
             }
         }
     }
 
-    fn update_positions<F: FnMut(&V, &V) -> V>(&mut self, mut f: F) {
+    fn update_force_each_node_edge<F: Fn(&V, &V, &V) -> V>(&mut self, f: F) {
         let n = self.node_positions.len();
-        assert!(n == self.forces.len());
 
-        for i in self.lock_first_n_positions..n {
-            let new_pos = f(&self.node_positions[i], &self.forces[i]);
+        for s in 0..self.forces.len() {
+            let i = self.node_at(s);
+            if self.is_settled(i) {
+                continue;
+            }
+            for i1 in 0..n {
+                for &i2 in self.node_neighbors[i1].iter() {
+                    if i == i1 || i == i2 || i1 == i2 {
+                        continue;
+                    }
+                    let force = f(&self.node_positions[i],
+                                  &self.node_positions[i1],
+                                  &self.node_positions[i2]);
+                    self.forces[s].add_scaled(1.0, &force);
+                }
+            }
+        }
+    }
+
+    fn update_positions<F: FnMut(&V, &V) -> V>(&mut self, mut f: F) {
+        self.total_velocity = 0.0;
+        if !self.targets.is_empty() {
+            for s in 0..self.forces.len() {
+                let i = self.node_at(s);
+                if let Some((ref target, strength)) = self.targets[s] {
+                    let pull = target.sub(&self.node_positions[i]);
+                    self.forces[s].add_scaled(strength, &pull);
+                }
+            }
+        }
+        if let Some(filter) = self.force_filter {
+            for force in self.forces.iter_mut() {
+                *force = filter(force);
+            }
+        }
+
+        for s in 0..self.forces.len() {
+            let i = self.node_at(s);
+            if i < self.lock_first_n_positions {
+                continue;
+            }
+            if self.is_settled(i) || self.is_pinned(i) {
+                if let Some(velocity) = self.velocities.get_mut(s) {
+                    velocity.reset();
+                }
+                continue;
+            }
+            let mut new_pos = match self.damping {
+                Some(damping) => {
+                    let force = &self.forces[s];
+                    let length = force.length_squared().sqrt();
+                    let mut velocity = self.velocities[s].scale(damping);
+                    if length > 0.0 {
+                        velocity.add_scaled((1.0 - damping) / length, force);
+                    }
+                    // `f` moves by the full step in the direction of the
+                    // velocity. scaled down by the speed, which is at most
+                    // 1, it stays within the bounds `f` clips to.
+                    let pos = &self.node_positions[i];
+                    let full_step = f(pos, &velocity).sub(pos);
+                    let mut new_pos = pos.clone();
+                    new_pos.add_scaled(velocity.length_squared().sqrt(), &full_step);
+                    self.velocities[s] = velocity;
+                    new_pos
+                }
+                None => f(&self.node_positions[i], &self.forces[s]),
+            };
+            if let Some(Some(ref bounds)) = self.node_bounds.get(s) {
+                new_pos = new_pos.clip_within(&bounds.min, &bounds.max);
+            }
+            self.total_velocity += new_pos.distance_squared_to_point(&self.node_positions[i]);
             self.node_positions[i] = new_pos;
         }
+
+        self.positions_updated = true;
     }
 }
 
-pub fn layout_typical_2d<'a, 'b>(l: Option<f32>,
-                                 node_positions: &'a mut Vec<P2d>,
-                                 node_neighbors: &'b [Vec<usize>],
-                                 lock_first_n_positions: usize) {
-    let n = node_positions.len();
-    assert!(node_neighbors.len() == n);
+pub const DEFAULT_MAX_ITER: usize = 300;
+pub const DEFAULT_EPS: f32 = 0.01;
+#[deprecated(note = "the default temperature scales with the graph, see \
+                     `LayoutConfig::temperature`")]
+pub const DEFAULT_TEMPERATURE: f32 = 0.1;
+pub const DEFAULT_SPRING_EXPONENT: f32 = 2.0;
+pub const DEFAULT_APPROXIMATE_ABOVE: usize = 1000;
 
-    const MAX_ITER: usize = 300;
-    const EPS: f32 = 0.01;
+// The default temperature, relative to the ideal length.
+const TEMPERATURE_PER_LENGTH: f32 = 2.0;
+// With the default temperature, the step cools down to this fraction of
+// the step at which `Convergence::DistanceMoved` is met, so that the layout
+// settles within `max_iter` iterations.
+const SETTLE_MARGIN: f32 = 0.5;
+// With the default temperature and `Convergence::EnergyDelta`, the step
+// cools down to this fraction of the ideal length.
+const FINAL_STEP: f32 = 1e-3;
 
-    let temp = 0.1f32;
-    let dt = temp / (MAX_ITER as f32);
-    let min_pos = P2d(0.0, 0.0);
-    let max_pos = P2d(1.0, 1.0);
-    let step_fn = |iter| temp - (iter as f32 * dt);
+// The repulsion cutoff chosen automatically for large graphs, relative to
+// the ideal length.
+const APPROXIMATE_CUTOFF: f32 = 3.0;
 
-    // `l`: ideal length of spring
-    let l: f32 = l.unwrap_or((1.0 / n as f32).sqrt());
+/// When to stop iterating before `max_iter` is reached.
+#[derive(Debug, Copy, Clone)]
+pub enum Convergence {
+    /// Once the distance moved by all nodes in an iteration together falls
+    /// below the given value.
+    DistanceMoved(f32),
+    /// Once the `energy` changes by less than the given fraction between two
+    /// iterations. Unlike the distance moved, this also detects layouts
+    /// which merely oscillate around their optimum.
+    EnergyDelta(f32),
+}
+
+/// The ideal length of a spring, if `n` nodes are laid out within the
+/// unit square: `sqrt(1 / n)`, so that each node gets about the same area.
+pub fn default_ideal_length(n: usize) -> f32 {
+    (1.0 / n as f32).sqrt()
+}
+
+/// How a layout run ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[must_use = "a layout may stop at `max_iter` without converging"]
+pub struct LayoutOutcome {
+    /// Number of iterations performed.
+    pub iterations: usize,
+    /// Whether the convergence criterion was met. `false` if the layout ran
+    /// out of iterations, in which case it may still be far from its
+    /// equilibrium, and a rerun with a larger `max_iter` can help.
+    pub converged: bool,
+    /// Whether the repulsion was approximated, as configured or chosen
+    /// automatically for a large graph (see `approximate_above`).
+    pub approximate: bool,
+}
+
+/// Parameters of `layout_typical_2d_with`.
+#[derive(Debug, Clone)]
+pub struct LayoutConfig {
+    /// Ideal length of a spring. Defaults to `default_ideal_length` if `None`.
+    pub ideal_length: Option<f32>,
+    pub max_iter: usize,
+    /// Defaults to `Convergence::DistanceMoved(DEFAULT_EPS)`.
+    pub convergence: Convergence,
+    /// Initial step size, which cools down linearly to zero over `max_iter`
+    /// iterations. If `None`, the default, it scales with the graph instead:
+    /// it starts at twice the ideal length, and cools down linearly to a
+    /// step at which the layout has settled by the last iteration, i.e. for
+    /// `DistanceMoved(eps)`, where all `n` nodes together move less than
+    /// `eps`. So small graphs take large steps, while large graphs take the
+    /// small steps they need to settle.
+    pub temperature: Option<f32>,
+    /// Skip nodes which settled. Off by default.
+    pub freeze: Option<Freeze>,
+    /// Push nodes away from edges they are not part of, with this strength
+    /// relative to the repulsion between nodes. Off by default, as it is
+    /// quite expensive: O(nodes * edges) per iteration.
+    pub node_edge_repulsion: Option<f32>,
+    /// After each iteration, translate the movable nodes so that their
+    /// centroid is at the given point. This keeps the layout from drifting.
+    /// Off by default.
+    pub recenter: Option<P2d>,
+    /// Scale the repulsion between nodes by their degrees, see
+    /// `Layout::scale_repulsion_by_degree`. Off by default.
+    pub degree_weighted_repulsion: bool,
+    /// Bounds of individual nodes, indexed by node. Nodes without an entry
+    /// are only kept within the unit square.
+    pub node_bounds: Vec<Option<Bounds<P2d>>>,
+    /// Move nodes with momentum, with the given damping factor in [0, 1),
+    /// see `Layout::momentum`. Off by default.
+    pub momentum: Option<f32>,
+    /// The attraction along an edge of length `d` is `d^spring_exponent / l`.
+    /// Defaults to `DEFAULT_SPRING_EXPONENT`, as proposed by Fruchterman and
+    /// Reingold. Higher values make the attraction grow faster with the
+    /// length of an edge: stronger for edges longer than 1, weaker for
+    /// shorter ones.
+    pub spring_exponent: f32,
+    /// Only repel nodes closer than this distance, which avoids visiting
+    /// all pairs of nodes. A few times the ideal length is a good choice.
+    /// Off by default.
+    pub repulsion_cutoff: Option<f32>,
+    /// Target position and spring strength of individual nodes, indexed by
+    /// node, see `Layout::set_target`.
+    pub targets: Vec<Option<(P2d, f32)>>,
+    /// Only repel nodes which are adjacent in the Delaunay triangulation of
+    /// their positions, rebuilt every that many iterations. This is much
+    /// cheaper than repelling all pairs, and spaces out nearly planar
+    /// graphs well. Cannot be combined with `repulsion_cutoff`. Off by
+    /// default.
+    pub delaunay_repulsion: Option<usize>,
+    /// Multiplies the repulsion between nodes, without changing the ideal
+    /// length. As nodes move along the total force, only the ratio to
+    /// `attraction_strength` matters. Neither affects the forces given to
+    /// `layout_typical_2d_custom`. Defaults to 1.
+    pub repulsion_strength: f32,
+    /// Multiplies the attraction along edges. Defaults to 1.
+    pub attraction_strength: f32,
+    /// For graphs with more nodes than this, approximate the repulsion by
+    /// a `repulsion_cutoff` of a few times the ideal length, unless another
+    /// approximation is configured. `None` to always compute it exactly.
+    /// Defaults to `DEFAULT_APPROXIMATE_ABOVE`.
+    pub approximate_above: Option<usize>,
+    /// Place nodes without edges on a grid within a strip of this height
+    /// at the `y = 1` edge, and keep the other nodes out of it, see
+    /// `Layout::tidy_isolated_nodes`. Off by default.
+    pub isolated_strip: Option<f32>,
+    /// Grow the bounds, starting with the unit square, while many nodes are
+    /// held at them, as the layout then needs more room than the ideal
+    /// length leaves it. The nodes may thus end up outside of the unit
+    /// square, see `transform::normalize_to_unit_box`. Off by default.
+    pub auto_grow_bounds: bool,
+    /// Keep the nodes within the disk inscribed into the bounds, instead of
+    /// the whole box, which packs them in a circle. Off by default.
+    pub disk: bool,
+    /// Rank of each node, indexed by node, and the strength in `(0, 1]`, by
+    /// which the nodes are pulled towards an x-order following their ranks
+    /// after each iteration, see `Layout::order_by_rank`. Off by default.
+    pub x_order: Option<(Vec<f32>, f32)>,
+    /// Keep the nodes at least this far inside the unit square, e.g. the
+    /// node radius in layout units, so that nodes at the bounds are not cut
+    /// off when drawn. Must be in `[0, 0.5)`. Defaults to 0.
+    pub padding: f32,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> LayoutConfig {
+        LayoutConfig {
+            ideal_length: None,
+            max_iter: DEFAULT_MAX_ITER,
+            convergence: Convergence::DistanceMoved(DEFAULT_EPS),
+            temperature: None,
+            freeze: None,
+            node_edge_repulsion: None,
+            recenter: None,
+            degree_weighted_repulsion: false,
+            node_bounds: Vec::new(),
+            momentum: None,
+            spring_exponent: DEFAULT_SPRING_EXPONENT,
+            repulsion_cutoff: None,
+            targets: Vec::new(),
+            delaunay_repulsion: None,
+            repulsion_strength: 1.0,
+            attraction_strength: 1.0,
+            approximate_above: Some(DEFAULT_APPROXIMATE_ABOVE),
+            isolated_strip: None,
+            auto_grow_bounds: false,
+            disk: false,
+            x_order: None,
+            padding: 0.0,
+        }
+    }
+}
+
+/// Lays out within the unit square using the default `LayoutConfig`.
+/// Returns the number of iterations performed, and whether the layout
+/// converged before running out of iterations.
+///
+/// Edges listed multiple times in `node_neighbors` attract their nodes
+/// multiple times, and so do edges stored in both directions. Use
+/// `adjacency::collapse_parallel_edges` to count each edge only once.
+/// Self-loops (see `adjacency::has_self_loops`) are ignored.
+///
+/// Graphs with more than `DEFAULT_APPROXIMATE_ABOVE` nodes are laid out
+/// with approximate repulsion, to stay fast.
+///
+/// The layout is deterministic: identical input produces bit-identical
+/// positions.
+pub fn layout_typical_2d(l: Option<f32>,
+                         node_positions: &mut Vec<P2d>,
+                         node_neighbors: &[Vec<usize>],
+                         lock_first_n_positions: usize)
+                         -> Result<LayoutOutcome, GraphLayoutError> {
+    let config = LayoutConfig { ideal_length: l, ..LayoutConfig::default() };
+    layout_typical_2d_with(&config,
+                           node_positions,
+                           node_neighbors,
+                           lock_first_n_positions)
+}
 
-    let k_r = l * l;
-    let k_s = l;
+/// Lays out within the unit square. Returns the `LayoutOutcome`, or an
+/// error if the adjacency lists do not match the nodes,
+/// or a parameter of `config` is out of range.
+pub fn layout_typical_2d_with(config: &LayoutConfig,
+                              node_positions: &mut Vec<P2d>,
+                              node_neighbors: &[Vec<usize>],
+                              lock_first_n_positions: usize)
+                              -> Result<LayoutOutcome, GraphLayoutError> {
+    let mut lay = Layout::new(node_positions, node_neighbors)?;
+    lay.lock_first_n_positions(lock_first_n_positions);
+    lay.configure(config)?;
+    Ok(layout_unit_square(config, &mut lay))
+}
+
+/// Lays out many small graphs, each given by its number of nodes and its
+/// adjacency lists, like `layout_typical_2d_with`, starting from random
+/// positions drawn from a generator seeded with `seed` for each graph.
+/// Only the forces are kept in a buffer shared by all graphs; other
+/// per-node state enabled by `config` is still allocated for each graph.
+/// Returns the positions of each graph with the outcome of its layout.
+pub fn layout_batch(config: &LayoutConfig,
+                    graphs: &[(usize, Vec<Vec<usize>>)],
+                    seed: u64)
+                    -> Result<Vec<(Vec<P2d>, LayoutOutcome)>, GraphLayoutError> {
+    let max_nodes = graphs.iter().map(|&(n, _)| n).max().unwrap_or(0);
+    let mut forces = vec![P2d::zero(); max_nodes];
+    let mut layouts = Vec::with_capacity(graphs.len());
+    for &(n, ref node_neighbors) in graphs.iter() {
+        let mut node_positions = initial_positions_random(n, &mut XorShiftRng::new(seed));
+        let outcome = {
+            let mut lay = Layout::with_forces(&mut node_positions,
+                                              node_neighbors,
+                                              &mut forces[..n])?;
+            lay.configure(config)?;
+            layout_unit_square(config, &mut lay)
+        };
+        layouts.push((node_positions, outcome));
+    }
+    Ok(layouts)
+}
+
+/// Like `layout_typical_2d_with`, but passes a snapshot of the positions
+/// after each iteration to `sink`, serialized by `json_writer::json_frame`,
+/// e.g. to forward it over a WebSocket to a live visualization. Iterations
+/// are counted from 1, so the last frame is that of the outcome.
+pub fn layout_streaming(config: &LayoutConfig,
+                        node_positions: &mut Vec<P2d>,
+                        node_neighbors: &[Vec<usize>],
+                        lock_first_n_positions: usize,
+                        sink: &mut dyn FnMut(&str))
+                        -> Result<LayoutOutcome, GraphLayoutError> {
+    let mut lay = Layout::new(node_positions, node_neighbors)?;
+    lay.lock_first_n_positions(lock_first_n_positions);
+    lay.configure(config)?;
+    let mut iteration = 0;
+    Ok(layout_unit_square_observed(config,
+                                   &mut lay,
+                                   &mut |positions| {
+                                       iteration += 1;
+                                       sink(&json_frame(iteration, positions));
+                                   }))
+}
 
-    let mut lay = Layout::new(node_positions, node_neighbors);
+/// Like `layout_typical_2d_with`, but with custom forces instead of the
+/// Fruchterman-Reingold ones. Both `attract` and `repel` are called with
+/// the positions of two nodes and return the force acting on the first
+/// one; the opposite force acts on the second. `attract` is called for
+/// adjacent nodes, `repel` for all pairs of nodes.
+pub fn layout_typical_2d_custom<A, R>(config: &LayoutConfig,
+                                      node_positions: &mut Vec<P2d>,
+                                      node_neighbors: &[Vec<usize>],
+                                      lock_first_n_positions: usize,
+                                      attract: A,
+                                      repel: R)
+                                      -> Result<LayoutOutcome, GraphLayoutError>
+    where A: Fn(&P2d, &P2d) -> P2d,
+          R: Fn(&P2d, &P2d) -> P2d
+{
+    let mut lay = Layout::new(node_positions, node_neighbors)?;
     lay.lock_first_n_positions(lock_first_n_positions);
-    layout(&mut lay,
-           step_fn,
-           MAX_ITER,
-           EPS,
-           k_r,
-           k_s,
-           &min_pos,
-           &max_pos);
+    lay.configure(config)?;
+    let l = ideal_length(config, lay.node_positions.len());
+    Ok(layout_unit_square_with(config, &mut lay, l, attract, repel, &mut |_| ()))
+}
+
+/// Like `layout_typical_2d_with`, but only the nodes in `subset` are
+/// moved. All other nodes stay where they are, but still exert forces.
+pub fn layout_subset(config: &LayoutConfig,
+                     node_positions: &mut Vec<P2d>,
+                     node_neighbors: &[Vec<usize>],
+                     subset: &[usize])
+                     -> Result<LayoutOutcome, GraphLayoutError> {
+    let mut lay = Layout::new_subset(node_positions, node_neighbors, subset)?;
+    lay.configure(config)?;
+    Ok(layout_unit_square(config, &mut lay))
+}
+
+/// Lays out a bipartite graph in two columns: nodes with `partition[i]`
+/// set to `false` are placed at `x = 0`, the others at `x = 1`. Only the
+/// y-coordinates are subject to the forces.
+pub fn layout_bipartite_2d(config: &LayoutConfig,
+                           node_positions: &mut Vec<P2d>,
+                           node_neighbors: &[Vec<usize>],
+                           partition: &[bool])
+                           -> Result<LayoutOutcome, GraphLayoutError> {
+    if partition.len() != node_positions.len() {
+        return Err(GraphLayoutError::LengthMismatch {
+            expected: node_positions.len(),
+            actual: partition.len(),
+        });
+    }
+    for (pos, &right) in node_positions.iter_mut().zip(partition.iter()) {
+        pos.0 = if right { 1.0 } else { 0.0 };
+    }
+
+    let mut lay = Layout::new(node_positions, node_neighbors)?;
+    lay.configure(config)?;
+    lay.force_filter = Some(|force: &P2d| P2d(0.0, force.1));
+    Ok(layout_unit_square(config, &mut lay))
+}
+
+// Seed of the jitter of the initial positions of `layout_1d`.
+const LAYOUT_1D_SEED: u64 = 1;
+
+/// Lays out the nodes along a line, by Fruchterman-Reingold restricted to
+/// the x-axis, within `[0, 1]`, with an ideal length of `1 / n`. As nodes
+/// can hardly pass each other in one dimension, it starts from the hop
+/// distances to a node at one end of the graph, which are then refined in
+/// `iterations` iterations of small steps. The result is deterministic.
+/// Being cheap, it makes a good seed for the x-coordinates of a 2d layout
+/// of elongated graphs, see `initial::initial_positions_from_1d`.
+pub fn layout_1d(node_neighbors: &[Vec<usize>],
+                 iterations: usize)
+                 -> Result<Vec<f32>, GraphLayoutError> {
+    let n = node_neighbors.len();
+    adjacency::validate(n, node_neighbors)?;
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+
+    // the node farthest from node 0 is at one end of its component.
+    let farthest = |distances: &[Option<usize>]| {
+        (0..n).max_by_key(|&i| (distances[i], n - i)).unwrap()
+    };
+    let end = farthest(&adjacency::bfs_distances(node_neighbors, 0));
+    let distances = adjacency::bfs_distances(node_neighbors, end);
+    let max = distances.iter().flatten().cloned().max().unwrap_or(0).max(1) as f32;
+
+    // nodes at the same distance are jittered apart, unreachable ones are
+    // spread at random.
+    let mut rng = XorShiftRng::new(LAYOUT_1D_SEED);
+    let mut node_positions: Vec<P2d> =
+        distances.iter()
+                 .map(|d| {
+                     let x = match *d {
+                         Some(d) => (d as f32 + 0.5 * rng.next_f32()) / (max + 0.5),
+                         None => rng.next_f32(),
+                     };
+                     P2d(x, 0.5)
+                 })
+                 .collect();
+
+    // small steps, so that nodes do not jump over their neighbors.
+    let l = 1.0 / n as f32;
+    let config = LayoutConfig {
+        ideal_length: Some(l),
+        max_iter: iterations,
+        temperature: Some(0.25 * l),
+        ..LayoutConfig::default()
+    };
+    {
+        let mut lay = Layout::new(&mut node_positions, node_neighbors)?;
+        lay.configure(&config)?;
+        lay.force_filter = Some(|force: &P2d| P2d(force.0, 0.0));
+        let _ = layout_unit_square(&config, &mut lay);
+    }
+    Ok(node_positions.iter().map(|pos| pos.0).collect())
+}
+
+/// Lays out the graph `k` times with `layout_typical_2d_with`, each time
+/// from other random positions, and keeps the layout of lowest `energy`,
+/// as FR ends up in different local minima. The random positions are drawn
+/// from a generator seeded with `seed`, so the result is deterministic.
+/// Returns the best positions with their energy.
+pub fn layout_best_of(config: &LayoutConfig,
+                      node_neighbors: &[Vec<usize>],
+                      k: usize,
+                      seed: u64)
+                      -> Result<(Vec<P2d>, f32), GraphLayoutError> {
+    if k == 0 {
+        return Err(GraphLayoutError::InvalidParameter("k"));
+    }
+    let n = node_neighbors.len();
+    let (k_r, k_s) = force_constants(config, ideal_length(config, n));
+    let mut rng = XorShiftRng::new(seed);
+    let mut best: Option<(Vec<P2d>, f32)> = None;
+    for _ in 0..k {
+        let mut node_positions = initial_positions_random(n, &mut rng);
+        let _ = layout_typical_2d_with(config, &mut node_positions, node_neighbors, 0)?;
+        let energy = energy_with_exponent(&node_positions,
+                                          node_neighbors,
+                                          k_r,
+                                          k_s,
+                                          config.spring_exponent);
+        if best.as_ref().is_none_or(|&(_, best_energy)| energy < best_energy) {
+            best = Some((node_positions, energy));
+        }
+    }
+    Ok(best.unwrap())
+}
+
+/// A budget of iterations shared by many layout runs, e.g. to relax the
+/// layout after each edit in an interactive editor, without ever spending
+/// more than a fixed number of iterations in total.
+#[derive(Debug, Clone)]
+pub struct RelaxBudget {
+    remaining: usize,
+}
+
+impl RelaxBudget {
+    pub fn new(total_iterations: usize) -> RelaxBudget {
+        RelaxBudget { remaining: total_iterations }
+    }
+
+    /// The number of iterations left.
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// Like `layout_typical_2d_with`, starting from the current positions,
+    /// but performs at most the remaining number of iterations, which
+    /// cools the temperature faster. Does nothing once the budget is
+    /// exhausted.
+    pub fn relax(&mut self,
+                 config: &LayoutConfig,
+                 node_positions: &mut Vec<P2d>,
+                 node_neighbors: &[Vec<usize>],
+                 lock_first_n_positions: usize)
+                 -> Result<LayoutOutcome, GraphLayoutError> {
+        if self.is_exhausted() {
+            return Ok(LayoutOutcome {
+                iterations: 0,
+                converged: false,
+                approximate: false,
+            });
+        }
+        let config = LayoutConfig {
+            max_iter: config.max_iter.min(self.remaining),
+            ..config.clone()
+        };
+        let outcome = layout_typical_2d_with(&config,
+                                             node_positions,
+                                             node_neighbors,
+                                             lock_first_n_positions)?;
+        self.remaining -= outcome.iterations;
+        Ok(outcome)
+    }
+}
+
+// Strength of the springs holding existing nodes in an incremental layout.
+const INCREMENTAL_ANCHOR: f32 = 10.0;
+
+/// Lays out a graph which changed since it was last laid out, without
+/// moving its nodes around too much. `previous` holds the last positions
+/// of the nodes by their stable id, and `node_ids[i]` is the id of node
+/// `i` of the new graph. Nodes which were there before start at their
+/// previous position, and are held there by springs (unless `targets` are
+/// configured), new ones start next to the average position of their
+/// known neighbors. Returns the new positions, with the outcome of the
+/// layout.
+pub fn layout_incremental_2d<K>(config: &LayoutConfig,
+                                previous: &HashMap<K, P2d>,
+                                node_ids: &[K],
+                                node_neighbors: &[Vec<usize>])
+                                -> Result<(Vec<P2d>, LayoutOutcome), GraphLayoutError>
+    where K: Hash + Eq
+{
+    let n = node_ids.len();
+    adjacency::validate(n, node_neighbors)?;
+
+    let mut incident: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for (i1, i2) in adjacency::edges(node_neighbors) {
+        incident[i1].push(i2);
+        incident[i2].push(i1);
+    }
+
+    let mut known: Vec<Option<P2d>> = node_ids.iter()
+                                              .map(|id| previous.get(id).cloned())
+                                              .collect();
+    // new nodes next to new nodes are placed once their neighbors are.
+    loop {
+        let placed: Vec<(usize, P2d)> =
+            (0..n).filter(|&i| known[i].is_none())
+                  .filter_map(|i| {
+                      let positions: Vec<P2d> =
+                          incident[i].iter().filter_map(|&j| known[j]).collect();
+                      if positions.is_empty() {
+                          return None;
+                      }
+                      let mut sum = P2d(0.0, 0.0);
+                      for pos in positions.iter() {
+                          sum.add_scaled(1.0, pos);
+                      }
+                      Some((i, sum.scale(1.0 / positions.len() as f32)))
+                  })
+                  .collect();
+        if placed.is_empty() {
+            break;
+        }
+        for (i, pos) in placed {
+            known[i] = Some(pos);
+        }
+    }
+
+    // nodes at the same position do not repel each other, so new nodes are
+    // spread on a small spiral (by the golden angle) around their start.
+    let radius = 0.1 * ideal_length(config, n);
+    let mut node_positions = Vec::with_capacity(n);
+    for (i, pos) in known.into_iter().enumerate() {
+        if previous.contains_key(&node_ids[i]) {
+            node_positions.push(pos.unwrap());
+        } else {
+            let pos = pos.unwrap_or(P2d(0.5, 0.5));
+            let angle = i as f32 * 2.399_963;
+            node_positions.push(P2d(pos.0 + radius * angle.cos(), pos.1 + radius * angle.sin()));
+        }
+    }
+
+    // keep the existing nodes close to their previous positions.
+    let mut config = config.clone();
+    if config.targets.is_empty() {
+        config.targets = node_ids.iter()
+                                 .map(|id| previous.get(id).map(|&pos| (pos, INCREMENTAL_ANCHOR)))
+                                 .collect();
+    }
+
+    let outcome = layout_typical_2d_with(&config, &mut node_positions, node_neighbors, 0)?;
+    Ok((node_positions, outcome))
+}
+
+// Bounds and maximal number of steps of the search for the ideal length.
+const TUNE_MIN_LENGTH: f32 = 1e-3;
+const TUNE_MAX_LENGTH: f32 = 1.0;
+const TUNE_MAX_STEPS: usize = 20;
+
+/// Searches the ideal length for which `layout_typical_2d_with`, started
+/// from `seed_positions`, yields an average edge length within `tolerance`
+/// of `target_edge_length`. The average edge length grows with the ideal
+/// length, so that a bisection finds it with a few layouts, which can be
+/// shortened by a small `max_iter` in `config`. Returns the ideal length
+/// which came closest, or `default_ideal_length` if there are no edges.
+pub fn tune_ideal_length(config: &LayoutConfig,
+                         seed_positions: &[P2d],
+                         node_neighbors: &[Vec<usize>],
+                         target_edge_length: f32,
+                         tolerance: f32)
+                         -> Result<f32, GraphLayoutError> {
+    let n = seed_positions.len();
+    adjacency::validate(n, node_neighbors)?;
+    if !(target_edge_length.is_finite() && target_edge_length > 0.0) {
+        return Err(GraphLayoutError::InvalidParameter("target_edge_length"));
+    }
+    if !(tolerance.is_finite() && tolerance >= 0.0) {
+        return Err(GraphLayoutError::InvalidParameter("tolerance"));
+    }
+    if node_neighbors.iter().all(|neighbors| neighbors.is_empty()) {
+        return Ok(default_ideal_length(n));
+    }
+
+    // bisect on a logarithmic scale, as the lengths span orders of magnitude.
+    let (mut lo, mut hi) = (TUNE_MIN_LENGTH.ln(), TUNE_MAX_LENGTH.ln());
+    let mut best = (f32::INFINITY, default_ideal_length(n));
+    for _ in 0..TUNE_MAX_STEPS {
+        let l = (0.5 * (lo + hi)).exp();
+        let mut node_positions = seed_positions.to_vec();
+        let config = LayoutConfig { ideal_length: Some(l), ..config.clone() };
+        let _ = layout_typical_2d_with(&config, &mut node_positions, node_neighbors, 0)?;
+
+        let lengths = edge_lengths(&node_positions, node_neighbors);
+        let average = lengths.iter().sum::<f32>() / lengths.len() as f32;
+        let error = (average - target_edge_length).abs();
+        if error < best.0 {
+            best = (error, l);
+        }
+        if error <= tolerance {
+            break;
+        }
+        if average < target_edge_length {
+            lo = l.ln();
+        } else {
+            hi = l.ln();
+        }
+    }
+    Ok(best.1)
+}
+
+// Checks the parameters of `config` for a graph of `n` nodes.
+fn validate_config(config: &LayoutConfig, n: usize) -> Result<(), GraphLayoutError> {
+    let positive = |v: f32| v.is_finite() && v > 0.0;
+    if !config.ideal_length.is_none_or(positive) {
+        return Err(GraphLayoutError::InvalidParameter("ideal_length"));
+    }
+    if config.temperature.is_some_and(|temp| !positive(temp)) {
+        return Err(GraphLayoutError::InvalidParameter("temperature"));
+    }
+    if !positive(config.spring_exponent) {
+        return Err(GraphLayoutError::InvalidParameter("spring_exponent"));
+    }
+    if !positive(config.repulsion_strength) {
+        return Err(GraphLayoutError::InvalidParameter("repulsion_strength"));
+    }
+    if !positive(config.attraction_strength) {
+        return Err(GraphLayoutError::InvalidParameter("attraction_strength"));
+    }
+    if !config.repulsion_cutoff.is_none_or(positive) {
+        return Err(GraphLayoutError::InvalidParameter("repulsion_cutoff"));
+    }
+    if config.delaunay_repulsion == Some(0) ||
+       config.delaunay_repulsion.is_some() && config.repulsion_cutoff.is_some() {
+        return Err(GraphLayoutError::InvalidParameter("delaunay_repulsion"));
+    }
+    if !(0.0..0.5).contains(&config.padding) {
+        return Err(GraphLayoutError::InvalidParameter("padding"));
+    }
+    if !config.isolated_strip.is_none_or(|height| height > 0.0 && height < 1.0) {
+        return Err(GraphLayoutError::InvalidParameter("isolated_strip"));
+    }
+    if !config.momentum.is_none_or(|damping| (0.0..1.0).contains(&damping)) {
+        return Err(GraphLayoutError::InvalidParameter("momentum"));
+    }
+    if config.node_bounds.len() > n {
+        return Err(GraphLayoutError::LengthMismatch {
+            expected: n,
+            actual: config.node_bounds.len(),
+        });
+    }
+    if config.targets.len() > n {
+        return Err(GraphLayoutError::LengthMismatch {
+            expected: n,
+            actual: config.targets.len(),
+        });
+    }
+    if config.targets
+             .iter()
+             .flatten()
+             .any(|&(_, strength)| !(strength.is_finite() && strength >= 0.0)) {
+        return Err(GraphLayoutError::InvalidParameter("targets"));
+    }
+    if let Some((ref ranks, strength)) = config.x_order {
+        if ranks.len() != n {
+            return Err(GraphLayoutError::LengthMismatch {
+                expected: n,
+                actual: ranks.len(),
+            });
+        }
+        if !(strength > 0.0 && strength <= 1.0) || ranks.iter().any(|r| !r.is_finite()) {
+            return Err(GraphLayoutError::InvalidParameter("x_order"));
+        }
+    }
+    Ok(())
+}
+
+// `l`: ideal length of spring
+fn ideal_length(config: &LayoutConfig, n: usize) -> f32 {
+    config.ideal_length.unwrap_or_else(|| default_ideal_length(n))
+}
+
+// `k_r` and `k_s` for the ideal length `l`, which scale the repulsive and
+// the attractive force, adjusted by the strengths of `config`.
+fn force_constants(config: &LayoutConfig, l: f32) -> (f32, f32) {
+    (l * l * config.repulsion_strength, l / config.attraction_strength)
+}
+
+// The step size of each iteration, as described for
+// `LayoutConfig::temperature`.
+fn cooling_schedule(config: &LayoutConfig, n: usize, l: f32) -> impl Fn(usize) -> f32 {
+    let max_iter = config.max_iter;
+    let (temp, final_step) = match config.temperature {
+        Some(temp) => (temp, None),
+        None => {
+            let final_step = match config.convergence {
+                Convergence::DistanceMoved(eps) => SETTLE_MARGIN * eps / n.max(1) as f32,
+                Convergence::EnergyDelta(_) => FINAL_STEP * l,
+            };
+            let temp = TEMPERATURE_PER_LENGTH * l;
+            (temp, Some(final_step.min(temp)))
+        }
+    };
+    move |iter| match final_step {
+        None => temp - (iter as f32 * (temp / max_iter as f32)),
+        Some(final_step) if max_iter > 1 => {
+            temp - (temp - final_step) * (iter as f32 / (max_iter - 1) as f32)
+        }
+        Some(_) => temp,
+    }
+}
+
+// The corners of the unit square, shrunk by the padding of `config`.
+fn padded_unit_square(config: &LayoutConfig) -> (P2d, P2d) {
+    let p = config.padding;
+    (P2d(p, p), P2d(1.0 - p, 1.0 - p))
+}
+
+// Whether to stop after an iteration, which moved the nodes by `dist_moved`.
+// `prev_energy` keeps track of the energy of the previous iteration.
+fn has_converged(config: &LayoutConfig,
+                 lay: &Layout<P2d>,
+                 dist_moved: f32,
+                 k_r: f32,
+                 k_s: f32,
+                 prev_energy: &mut Option<f32>)
+                 -> bool {
+    match config.convergence {
+        Convergence::DistanceMoved(eps) => dist_moved < eps,
+        Convergence::EnergyDelta(eps) => {
+            let e = energy_with_exponent(lay.node_positions,
+                                         lay.node_neighbors,
+                                         k_r,
+                                         k_s,
+                                         config.spring_exponent);
+            let converged = match *prev_energy {
+                Some(prev) => (e - prev).abs() <= eps * prev.abs(),
+                None => false,
+            };
+            *prev_energy = Some(e);
+            converged
+        }
+    }
+}
+
+// With `auto_grow_bounds`, the bounds grow by `GROW_MARGIN` times their
+// size on each side, once more than `GROW_PRESSURE` of the movable nodes
+// were held at them for `GROW_AFTER` consecutive iterations.
+const GROW_PRESSURE: f32 = 0.1;
+const GROW_AFTER: usize = 3;
+const GROW_MARGIN: f32 = 0.1;
+
+// Lays out within the unit square, as configured by `config`.
+fn layout_unit_square(config: &LayoutConfig, lay: &mut Layout<P2d>) -> LayoutOutcome {
+    layout_unit_square_observed(config, lay, &mut |_| ())
+}
+
+// Like `layout_unit_square`, but calls `observe` with the positions after
+// each iteration.
+fn layout_unit_square_observed(config: &LayoutConfig,
+                               lay: &mut Layout<P2d>,
+                               observe: &mut dyn FnMut(&[P2d]))
+                               -> LayoutOutcome {
+    let l = ideal_length(config, lay.node_positions.len());
+
+    let (k_r, k_s) = force_constants(config, l);
+    let exponent = config.spring_exponent;
+
+    layout_unit_square_with(config,
+                            lay,
+                            l,
+                            |pos1, pos2| {
+                                attractive_force(pos1, pos2, k_s, exponent).scale(-1.0)
+                            },
+                            |pos1, pos2| repulsive_force(pos1, pos2, k_r),
+                            observe)
+}
+
+// Like `layout_unit_square_observed`, but with the given forces between
+// adjacent and between all pairs of nodes.
+fn layout_unit_square_with<A, R>(config: &LayoutConfig,
+                                 lay: &mut Layout<P2d>,
+                                 l: f32,
+                                 attract: A,
+                                 repel: R,
+                                 observe: &mut dyn FnMut(&[P2d]))
+                                 -> LayoutOutcome
+    where A: Fn(&P2d, &P2d) -> P2d,
+          R: Fn(&P2d, &P2d) -> P2d
+{
+    let n = lay.node_positions.len();
+    let (mut min_pos, mut max_pos) = padded_unit_square(config);
+    let step_fn = cooling_schedule(config, n, l);
+
+    let (k_r, k_s) = force_constants(config, l);
+
+    // consecutive iterations with many nodes held at the bounds.
+    let mut pressured_iterations = 0;
+    let cutoff = repulsion_cutoff(config, n, l);
+
+    let mut prev_energy = None;
+    let mut converged = false;
+    let mut iter: usize = 0;
+    while iter < config.max_iter {
+        let step = step_fn(iter);
+        if config.delaunay_repulsion.is_some_and(|k| iter.is_multiple_of(k)) {
+            lay.triangulate();
+        }
+        iter += 1;
+
+        update_forces_2d(lay, config, cutoff, k_r, &attract, &repel);
+        let dist_moved = move_positions(lay, step, &min_pos, &max_pos);
+        constrain_2d(lay, config, &mut min_pos, &mut max_pos, &mut pressured_iterations);
+        observe(lay.node_positions);
+        if has_converged(config, lay, dist_moved, k_r, k_s, &mut prev_energy) {
+            converged = true;
+            break;
+        }
+    }
+    LayoutOutcome {
+        iterations: iter,
+        converged,
+        approximate: cutoff.is_some() || config.delaunay_repulsion.is_some(),
+    }
+}
+
+// The distance beyond which nodes do not repel each other, if any. Large
+// graphs get one automatically, unless Delaunay repulsion is used.
+fn repulsion_cutoff(config: &LayoutConfig, n: usize, l: f32) -> Option<f32> {
+    match config.repulsion_cutoff {
+        None if config.delaunay_repulsion.is_none() &&
+                config.approximate_above.is_some_and(|max| n > max) => Some(APPROXIMATE_CUTOFF * l),
+        cutoff => cutoff,
+    }
+}
+
+// Like the forces of `iterate`, but with the additional forces enabled in
+// `config`. Only repels nodes closer than `cutoff`, if given.
+fn update_forces_2d<A, R>(lay: &mut Layout<P2d>,
+                          config: &LayoutConfig,
+                          cutoff: Option<f32>,
+                          k_r: f32,
+                          attract: &A,
+                          repel: &R)
+    where A: Fn(&P2d, &P2d) -> P2d,
+          R: Fn(&P2d, &P2d) -> P2d
+{
+    lay.reset_forces();
+    if config.delaunay_repulsion.is_some() {
+        lay.update_force_each_delaunay_pair(repel);
+    } else if let Some(cutoff) = cutoff {
+        lay.update_force_each_node_pair_within(cutoff, repel);
+    } else {
+        lay.update_force_each_node_pair(repel);
+    }
+    lay.update_force_each_edge(attract);
+
+    if let Some(strength) = config.node_edge_repulsion {
+        let k = strength * k_r;
+        lay.update_force_each_node_edge(|pos, pos1, pos2| {
+            node_edge_repulsive_force(pos, pos1, pos2, k)
+        });
+    }
+}
+
+// Applies the constraints of `config` to the positions after an iteration,
+// and grows the bounds `min_pos` and `max_pos` under pressure, counting
+// the consecutive iterations with many nodes held at the bounds in
+// `pressured_iterations`.
+fn constrain_2d(lay: &mut Layout<P2d>,
+                config: &LayoutConfig,
+                min_pos: &mut P2d,
+                max_pos: &mut P2d,
+                pressured_iterations: &mut usize) {
+    if let Some(ref target) = config.recenter {
+        lay.recenter(target, min_pos, max_pos);
+    }
+    if let Some((ref ranks, strength)) = config.x_order {
+        lay.order_by_rank(ranks, strength);
+    }
+    if config.disk {
+        let radius = 0.5 * (max_pos.0 - min_pos.0).min(max_pos.1 - min_pos.1);
+        lay.clip_to_disk(&P2d(0.5 * (min_pos.0 + max_pos.0), 0.5 * (min_pos.1 + max_pos.1)),
+                         radius);
+    }
+    if config.auto_grow_bounds {
+        if lay.clamped_fraction(min_pos, max_pos) > GROW_PRESSURE {
+            *pressured_iterations += 1;
+        } else {
+            *pressured_iterations = 0;
+        }
+        if *pressured_iterations >= GROW_AFTER {
+            let margin = P2d(GROW_MARGIN * (max_pos.0 - min_pos.0),
+                             GROW_MARGIN * (max_pos.1 - min_pos.1));
+            *min_pos = min_pos.sub(&margin);
+            max_pos.add_scaled(1.0, &margin);
+            *pressured_iterations = 0;
+        }
+    }
+}
+
+// Energy of the terms of `energy` which involve node `i`, if it were at `pos`.
+fn node_energy<V>(node_positions: &[V],
+                  incident: &[Vec<usize>],
+                  i: usize,
+                  pos: &V,
+                  k_r: f32,
+                  k_s: f32,
+                  exponent: f32)
+                  -> f32
+    where V: Vector<Scalar = f32>
+{
+    let mut sum = 0.0;
+    for (j, other) in node_positions.iter().enumerate() {
+        if j != i {
+            sum += repulsive_energy(pos, other, k_r);
+        }
+    }
+    for &j in incident[i].iter() {
+        sum += attractive_energy(pos, &node_positions[j], k_s, exponent);
+    }
+    sum
+}
+
+/// Like `layout_typical_2d_with`, but simulates annealing to escape local
+/// minima: each node is moved along its force plus a random displacement
+/// of up to the current temperature `T`. A move which raises the `energy`
+/// by `dE` is only accepted with probability `exp(-dE / T)`, where the
+/// pull towards a target adds to the energy as a spring of zero length.
+///
+/// Approximations of the repulsion only apply to the forces, moves are
+/// always judged by the exact energy. `LayoutConfig::momentum` is rejected
+/// as an invalid parameter, as rejected moves leave no velocity to keep.
+pub fn layout_annealing_2d(config: &LayoutConfig,
+                           node_positions: &mut Vec<P2d>,
+                           node_neighbors: &[Vec<usize>],
+                           lock_first_n_positions: usize,
+                           rng: &mut impl Rng)
+                           -> Result<LayoutOutcome, GraphLayoutError> {
+    let n = node_positions.len();
+    if config.momentum.is_some() {
+        return Err(GraphLayoutError::InvalidParameter("momentum"));
+    }
+
+    let mut lay = Layout::new(node_positions, node_neighbors)?;
+    lay.lock_first_n_positions(lock_first_n_positions);
+    lay.configure(config)?;
+
+    let (mut min_pos, mut max_pos) = padded_unit_square(config);
+    let mut pressured_iterations = 0;
+
+    let l = ideal_length(config, n);
+    let step_fn = cooling_schedule(config, n, l);
+    let cutoff = repulsion_cutoff(config, n, l);
+
+    let (k_r, k_s) = force_constants(config, l);
+    let exponent = config.spring_exponent;
+    let attract = |pos1: &P2d, pos2: &P2d| attractive_force(pos1, pos2, k_s, exponent).scale(-1.0);
+    let repel = |pos1: &P2d, pos2: &P2d| repulsive_force(pos1, pos2, k_r);
+
+    // edges of each node in both directions, self-loops have no energy.
+    let mut incident: Vec<Vec<usize>> = (0..n).map(|_| Vec::new()).collect();
+    for (i1, neighbors) in node_neighbors.iter().enumerate() {
+        for &i2 in neighbors.iter().filter(|&&i2| i2 != i1) {
+            incident[i1].push(i2);
+            incident[i2].push(i1);
+        }
+    }
+
+    let mut prev_energy = None;
+    let mut converged = false;
+    let mut iter: usize = 0;
+    while iter < config.max_iter {
+        let step = step_fn(iter);
+        if config.delaunay_repulsion.is_some_and(|k| iter.is_multiple_of(k)) {
+            lay.triangulate();
+        }
+        iter += 1;
+
+        update_forces_2d(&mut lay, config, cutoff, k_r, &attract, &repel);
+
+        let mut sum_distance = 0.0;
+        for i in 0..n {
+            if !lay.is_movable(i) {
+                continue;
+            }
+            let position = lay.node_positions[i];
+            let mut force = lay.forces[i];
+            let target = lay.targets.get(i).and_then(|&target| target);
+            if let Some((ref target, strength)) = target {
+                force.add_scaled(strength, &target.sub(&position));
+            }
+
+            let mut new_pos = position;
+            let length = force.length_squared().sqrt();
+            if length > 0.0 {
+                new_pos.add_scaled(step / length, &force);
+            }
+            new_pos.add_scaled(step,
+                               &P2d(2.0 * rng.next_f32() - 1.0, 2.0 * rng.next_f32() - 1.0));
+            let mut new_pos = new_pos.clip_within(&min_pos, &max_pos);
+            if let Some(Some(ref bounds)) = lay.node_bounds.get(i) {
+                new_pos = new_pos.clip_within(&bounds.min, &bounds.max);
+            }
+
+            let energy_at = |pos: &P2d| {
+                let pull = match target {
+                    Some((ref target, strength)) => {
+                        0.5 * strength * pos.distance_squared_to_point(target)
+                    }
+                    None => 0.0,
+                };
+                node_energy(lay.node_positions, &incident, i, pos, k_r, k_s, exponent) + pull
+            };
+            let delta = energy_at(&new_pos) - energy_at(&position);
+            if delta <= 0.0 || rng.next_f32() < (-delta / step).exp() {
+                sum_distance += new_pos.distance_squared_to_point(&position).sqrt();
+                lay.node_positions[i] = new_pos;
+            }
+        }
+        lay.positions_updated = true;
+        constrain_2d(&mut lay, config, &mut min_pos, &mut max_pos, &mut pressured_iterations);
+
+        if has_converged(config, &lay, sum_distance, k_r, k_s, &mut prev_energy) {
+            converged = true;
+            break;
+        }
+    }
+    Ok(LayoutOutcome {
+        iterations: iter,
+        converged,
+        approximate: cutoff.is_some() || config.delaunay_repulsion.is_some(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geometry::bounding_box;
+    use initial::initial_positions_random;
+    use rng::XorShiftRng;
+
+    fn ring(n: usize) -> Vec<Vec<usize>> {
+        (0..n).map(|i| vec![(i + 1) % n]).collect()
+    }
+
+    fn random_positions(n: usize, seed: u64) -> Vec<P2d> {
+        initial_positions_random(n, &mut XorShiftRng::new(seed))
+    }
+
+    // Runs `iterations` steps of fixed size within the unit square.
+    fn step_n(layout: &mut Layout<P2d>, iterations: usize, step: f32) {
+        let l = default_ideal_length(layout.positions().len());
+        for _ in 0..iterations {
+            let _ = layout.step(step, l * l, l, &P2d(0.0, 0.0), &P2d(1.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn higher_max_iter_runs_more_iterations() {
+        let node_neighbors = ring(10);
+        let run = |max_iter| {
+            let config = LayoutConfig {
+                max_iter,
+                convergence: Convergence::DistanceMoved(0.0),
+                ..LayoutConfig::default()
+            };
+            let mut node_positions = random_positions(10, 1);
+            layout_typical_2d_with(&config, &mut node_positions, &node_neighbors, 0)
+                .unwrap()
+                .iterations
+        };
+        assert_eq!(run(DEFAULT_MAX_ITER), DEFAULT_MAX_ITER);
+        assert_eq!(run(2 * DEFAULT_MAX_ITER), 2 * DEFAULT_MAX_ITER);
+    }
+
+    #[test]
+    fn annealing_escapes_a_twisted_ring() {
+        // a ring folded into a figure eight, which crosses itself. with
+        // small steps, plain FR cannot untangle it.
+        let n = 12;
+        let node_neighbors = ring(n);
+        let twisted: Vec<P2d> = (0..n)
+            .map(|i| {
+                let t = 2.0 * ::std::f32::consts::PI * i as f32 / n as f32;
+                P2d(0.5 + 0.4 * t.sin(), 0.5 + 0.2 * (2.0 * t).sin())
+            })
+            .collect();
+        let config = LayoutConfig { temperature: Some(0.05), ..LayoutConfig::default() };
+        let l = default_ideal_length(n);
+
+        let mut plain = twisted.clone();
+        let _ = layout_typical_2d_with(&config, &mut plain, &node_neighbors, 0).unwrap();
+        let mut annealed = twisted.clone();
+        let _ = layout_annealing_2d(&config,
+                                    &mut annealed,
+                                    &node_neighbors,
+                                    0,
+                                    &mut XorShiftRng::new(2))
+            .unwrap();
+        assert!(energy(&annealed, &node_neighbors, l * l, l) <
+                energy(&plain, &node_neighbors, l * l, l));
+    }
+
+    #[test]
+    fn annealing_applies_the_config() {
+        let n = 12;
+        let node_neighbors = ring(n);
+        let mut node_bounds = vec![None; n];
+        node_bounds[1] = Some(Bounds::new(P2d(0.0, 0.0), P2d(0.2, 0.2)));
+        let config = LayoutConfig {
+            node_bounds,
+            freeze: Some(Freeze { threshold: 1e-3, iterations: 3 }),
+            ..LayoutConfig::default()
+        };
+        let mut node_positions = random_positions(n, 5);
+        let locked = node_positions[0];
+        let _ = layout_annealing_2d(&config,
+                                    &mut node_positions,
+                                    &node_neighbors,
+                                    1,
+                                    &mut XorShiftRng::new(3))
+            .unwrap();
+        assert_eq!((node_positions[0].0, node_positions[0].1), (locked.0, locked.1));
+        let p = node_positions[1];
+        assert!(p.0 <= 0.2 && p.1 <= 0.2);
+
+        let config = LayoutConfig { momentum: Some(0.5), ..LayoutConfig::default() };
+        match layout_annealing_2d(&config,
+                                  &mut node_positions,
+                                  &node_neighbors,
+                                  0,
+                                  &mut XorShiftRng::new(3)) {
+            Err(GraphLayoutError::InvalidParameter("momentum")) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn out_of_range_neighbor_is_an_error() {
+        let node_neighbors = vec![vec![1], vec![3], vec![]];
+        let mut node_positions = random_positions(3, 1);
+        match layout_typical_2d(None, &mut node_positions, &node_neighbors, 0) {
+            Err(GraphLayoutError::InvalidNeighbor { node: 1, neighbor: 3 }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(Layout::<P2d>::new(&mut node_positions, &node_neighbors).is_err());
+    }
+
+    #[test]
+    fn identical_input_gives_bit_identical_layouts() {
+        let node_neighbors =
+            vec![vec![1, 2], vec![2, 3], vec![3, 4], vec![4, 0], vec![0, 5], vec![]];
+        let run = || {
+            let mut node_positions = random_positions(node_neighbors.len(), 7);
+            let _ = layout_typical_2d(None, &mut node_positions, &node_neighbors, 0).unwrap();
+            node_positions.iter().map(|p| (p.0.to_bits(), p.1.to_bits())).collect::<Vec<_>>()
+        };
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn frozen_nodes_settle_and_barely_change_the_layout() {
+        let n = 5;
+        let node_neighbors = ring(n);
+        let l = default_ideal_length(n);
+        let freeze = Freeze { threshold: 0.2 * l, iterations: 5 };
+
+        let mut node_positions = random_positions(n, 3);
+        let mut settled = Vec::new();
+        {
+            let mut layout = Layout::new(&mut node_positions, &node_neighbors).unwrap();
+            layout.freeze(freeze);
+            for iter in 0..DEFAULT_MAX_ITER {
+                let step = 0.5 * l * (1.0 - iter as f32 / DEFAULT_MAX_ITER as f32);
+                let _ = layout.step(step, l * l, l, &P2d(0.0, 0.0), &P2d(1.0, 1.0));
+                if iter % 20 == 19 {
+                    settled.push((0..n).filter(|&i| layout.is_settled(i)).count());
+                }
+            }
+        }
+        assert!(settled.windows(2).all(|w| w[0] <= w[1]));
+        assert!(settled[0] < settled[settled.len() - 1]);
+
+        let run = |freeze| {
+            let config = LayoutConfig { freeze, ..LayoutConfig::default() };
+            let mut node_positions = random_positions(n, 3);
+            let _ = layout_typical_2d_with(&config, &mut node_positions, &node_neighbors, 0)
+                .unwrap();
+            node_positions
+        };
+        for (frozen, free) in run(Some(freeze)).iter().zip(run(None).iter()) {
+            assert!(frozen.distance_squared_to_point(free).sqrt() < 0.01);
+        }
+    }
+
+    #[test]
+    fn pinned_node_stays_at_its_position() {
+        let node_neighbors = ring(6);
+        let mut node_positions = random_positions(6, 5);
+        let mut layout = Layout::new(&mut node_positions, &node_neighbors).unwrap();
+        layout.pin(2, P2d(0.3, 0.7));
+        for _ in 0..10 {
+            step_n(&mut layout, 5, 0.05);
+            assert_eq!((layout.positions()[2].0, layout.positions()[2].1), (0.3, 0.7));
+        }
+        layout.unpin(2);
+        step_n(&mut layout, 5, 0.05);
+        assert!(layout.positions()[2].distance_squared_to_point(&P2d(0.3, 0.7)) > 0.0);
+    }
+
+    #[test]
+    fn bipartite_columns_keep_their_x() {
+        // users 0..3 and items 3..6.
+        let node_neighbors = vec![vec![3, 4], vec![4, 5], vec![5, 3], vec![], vec![], vec![]];
+        let partition = [false, false, false, true, true, true];
+        let initial = random_positions(6, 11);
+        let mut node_positions = initial.clone();
+        let _ = layout_bipartite_2d(&LayoutConfig::default(),
+                                    &mut node_positions,
+                                    &node_neighbors,
+                                    &partition)
+            .unwrap();
+        for (pos, &right) in node_positions.iter().zip(partition.iter()) {
+            assert_eq!(pos.0, if right { 1.0 } else { 0.0 });
+        }
+        assert!(node_positions.iter().zip(initial.iter()).any(|(p, q)| p.1 != q.1));
+    }
+
+    #[test]
+    fn default_ideal_length_is_one_over_sqrt_n() {
+        for &n in &[1, 2, 10, 1000] {
+            assert_eq!(default_ideal_length(n), (1.0 / n as f32).sqrt());
+        }
+    }
+
+    #[test]
+    fn node_on_an_edge_is_pushed_off_it() {
+        // node 2 lies on the edge 0-1. by symmetry, the forces between the
+        // nodes alone keep it there.
+        let node_neighbors = vec![vec![1], vec![], vec![]];
+        let run = |node_edge_repulsion| {
+            let config = LayoutConfig { node_edge_repulsion, ..LayoutConfig::default() };
+            let mut node_positions = vec![P2d(0.2, 0.5), P2d(0.8, 0.5), P2d(0.5, 0.5)];
+            let _ = layout_typical_2d_with(&config, &mut node_positions, &node_neighbors, 0)
+                .unwrap();
+            node_positions[2]
+        };
+        assert_eq!(run(None).1, 0.5);
+        assert!((run(Some(1.0)).1 - 0.5).abs() > 0.1);
+    }
+
+    #[test]
+    fn custom_forces_without_repulsion_contract_edges() {
+        let node_neighbors = ring(5);
+        let mut node_positions = random_positions(5, 2);
+        let before: f32 = edge_lengths(&node_positions, &node_neighbors).iter().sum();
+        let k_s = default_ideal_length(5);
+        let _ = layout_typical_2d_custom(&LayoutConfig::default(),
+                                         &mut node_positions,
+                                         &node_neighbors,
+                                         0,
+                                         |p1, p2| {
+                                             attractive_force(p1, p2, k_s, 2.0).scale(-1.0)
+                                         },
+                                         |_, _| P2d(0.0, 0.0))
+            .unwrap();
+        let after: f32 = edge_lengths(&node_positions, &node_neighbors).iter().sum();
+        assert!(after < 0.1 * before);
+    }
+
+    #[test]
+    fn degree_scaling_spreads_the_leaves_of_a_star() {
+        // the hub 0 and its leaves, stored in both directions.
+        let n = 7;
+        let node_neighbors: Vec<Vec<usize>> =
+            (0..n).map(|i| if i == 0 { (1..n).collect() } else { vec![0] }).collect();
+        let mean_leaf_distance = |degree_weighted_repulsion| {
+            let config = LayoutConfig { degree_weighted_repulsion, ..LayoutConfig::default() };
+            let mut node_positions = random_positions(n, 4);
+            let _ = layout_typical_2d_with(&config, &mut node_positions, &node_neighbors, 0)
+                .unwrap();
+            (1..n)
+                .map(|i| node_positions[i].distance_squared_to_point(&node_positions[0]).sqrt())
+                .sum::<f32>() / (n - 1) as f32
+        };
+        assert!(mean_leaf_distance(true) > 1.2 * mean_leaf_distance(false));
+    }
+
+    fn centroid(node_positions: &[P2d]) -> P2d {
+        let mut sum = P2d(0.0, 0.0);
+        for pos in node_positions {
+            sum.add_scaled(1.0 / node_positions.len() as f32, pos);
+        }
+        sum
+    }
+
+    #[test]
+    fn recentered_centroid_stays_at_target_within_bounds() {
+        // small enough to stay clear of the bounds around the target.
+        let node_neighbors = ring(4);
+        let (min_pos, max_pos) = (P2d(0.0, 0.0), P2d(1.0, 1.0));
+        let mut node_positions = random_positions(4, 6);
+        let mut layout = Layout::new(&mut node_positions, &node_neighbors).unwrap();
+        for _ in 0..100 {
+            step_n(&mut layout, 1, 0.01);
+            layout.recenter(&P2d(0.5, 0.5), &min_pos, &max_pos);
+            let c = centroid(layout.positions());
+            assert!(c.distance_squared_to_point(&P2d(0.5, 0.5)).sqrt() < 1e-5);
+        }
+
+        // a target near the corner would push nodes out of the unit square.
+        layout.recenter(&P2d(0.05, 0.05), &min_pos, &max_pos);
+        for pos in layout.positions() {
+            assert!((0.0..=1.0).contains(&pos.0) && (0.0..=1.0).contains(&pos.1));
+        }
+    }
+
+    #[test]
+    fn phase_timings_are_populated() {
+        let n = 300;
+        let node_neighbors = ring(n);
+        let l = default_ideal_length(n);
+        let mut node_positions = random_positions(n, 8);
+        let mut layout = Layout::new(&mut node_positions, &node_neighbors).unwrap();
+        let (_, timings) =
+            iterate_timed(&mut layout, 0.01, l * l, l, &P2d(0.0, 0.0), &P2d(1.0, 1.0));
+        assert!(timings.repulsion > Duration::new(0, 0));
+        assert!(timings.attraction > Duration::new(0, 0));
+        assert!(timings.update > Duration::new(0, 0));
+    }
+
+    #[test]
+    fn subset_layout_only_moves_the_subset() {
+        let node_neighbors = vec![vec![1], vec![2], vec![]];
+        let initial = vec![P2d(0.1, 0.1), P2d(0.9, 0.9), P2d(0.3, 0.1)];
+        let mut node_positions = initial.clone();
+        let _ = layout_subset(&LayoutConfig::default(), &mut node_positions, &node_neighbors, &[1])
+            .unwrap();
+        for &i in &[0, 2] {
+            assert_eq!((node_positions[i].0, node_positions[i].1), (initial[i].0, initial[i].1));
+        }
+        // pulled towards its neighbors, which stay put.
+        let between = P2d(0.2, 0.1);
+        assert!(node_positions[1].distance_squared_to_point(&between) <
+                0.25 * initial[1].distance_squared_to_point(&between));
+    }
+
+    #[test]
+    fn energy_delta_stops_an_oscillation() {
+        // with a nearly constant large step, the two nodes jump back and
+        // forth between being `step` closer and farther than their ideal
+        // distance, which have about the same energy.
+        let node_neighbors = vec![vec![1], vec![]];
+        let half = 0.5 * (default_ideal_length(2) - 0.05);
+        let run = |convergence| {
+            let config = LayoutConfig {
+                max_iter: 20_000,
+                temperature: Some(0.05),
+                convergence,
+                ..LayoutConfig::default()
+            };
+            let mut node_positions = vec![P2d(0.5 - half, 0.5), P2d(0.5 + half, 0.5)];
+            layout_typical_2d_with(&config, &mut node_positions, &node_neighbors, 0)
+                .unwrap()
+                .iterations
+        };
+        assert!(run(Convergence::EnergyDelta(1e-3)) < 100);
+        assert!(run(Convergence::DistanceMoved(DEFAULT_EPS)) > 10_000);
+    }
+
+    #[test]
+    fn node_stays_within_its_own_bounds() {
+        let node_neighbors = ring(6);
+        let sub_box = Bounds::new(P2d(0.0, 0.0), P2d(0.3, 0.3));
+        let config = LayoutConfig {
+            node_bounds: vec![Some(sub_box), None, None, None, None, None],
+            ..LayoutConfig::default()
+        };
+        let mut node_positions = random_positions(6, 9);
+        node_positions[0] = P2d(0.1, 0.1);
+        let mut lay = Layout::new(&mut node_positions, &node_neighbors).unwrap();
+        lay.configure(&config).unwrap();
+        let mut others_outside = false;
+        let _ = layout_unit_square_observed(&config, &mut lay, &mut |node_positions| {
+            let p = node_positions[0];
+            assert!((0.0..=0.3).contains(&p.0) && (0.0..=0.3).contains(&p.1));
+            others_outside |= node_positions[1..].iter().any(|p| p.0 > 0.3 || p.1 > 0.3);
+        });
+        assert!(others_outside);
+    }
+
+    // The number of times a node moved in about the opposite direction of
+    // its previous move, over `iterations` steps of the given size.
+    fn reversals(layout: &mut Layout<P2d>, iterations: usize, step: f32) -> usize {
+        let n = layout.positions().len();
+        let mut last_moves = vec![P2d(0.0, 0.0); n];
+        let mut reversals = 0;
+        for _ in 0..iterations {
+            let before = layout.positions().to_vec();
+            step_n(layout, 1, step);
+            for i in 0..n {
+                let m = layout.positions()[i].sub(&before[i]);
+                if m.0 * last_moves[i].0 + m.1 * last_moves[i].1 < 0.0 {
+                    reversals += 1;
+                }
+                last_moves[i] = m;
+            }
+        }
+        reversals
+    }
+
+    #[test]
+    fn momentum_reverses_direction_less_often() {
+        let node_neighbors = ring(6);
+        let mut direct = random_positions(6, 10);
+        let mut damped = direct.clone();
+        let direct_reversals = {
+            let mut layout = Layout::new(&mut direct, &node_neighbors).unwrap();
+            reversals(&mut layout, 200, 0.02)
+        };
+        let damped_reversals = {
+            let mut layout = Layout::new(&mut damped, &node_neighbors).unwrap();
+            layout.momentum(0.8);
+            reversals(&mut layout, 200, 0.02)
+        };
+        assert!(damped_reversals < direct_reversals / 2,
+                "{} vs {}",
+                damped_reversals,
+                direct_reversals);
+    }
+
+    #[test]
+    fn duplicate_edges_pull_closer() {
+        let distance = |node_neighbors: &[Vec<usize>]| {
+            let mut node_positions = vec![P2d(0.2, 0.5), P2d(0.8, 0.5)];
+            let _ = layout_typical_2d(None, &mut node_positions, node_neighbors, 0).unwrap();
+            node_positions[0].distance_squared_to_point(&node_positions[1]).sqrt()
+        };
+        let tripled = vec![vec![1, 1, 1], vec![]];
+        assert!(distance(&tripled) < 0.8 * distance(&[vec![1], vec![]]));
+        assert_eq!(distance(&adjacency::collapse_parallel_edges(&tripled)),
+                   distance(&[vec![1], vec![]]));
+    }
+
+    #[test]
+    fn subset_layout_allocates_per_node_state_for_the_subset() {
+        let n = 10_000;
+        let node_neighbors = ring(n);
+        let mut node_positions = random_positions(n, 12);
+        let subset = [5, 500, 5000];
+        let mut layout = Layout::new_subset(&mut node_positions, &node_neighbors, &subset).unwrap();
+        layout.freeze(Freeze { threshold: 1e-3, iterations: 3 });
+        layout.momentum(0.5);
+        layout.pin(500, P2d(0.5, 0.5));
+        layout.set_node_bounds(5, Bounds::new(P2d(0.0, 0.0), P2d(0.5, 0.5)));
+        layout.set_target(5000, P2d(0.9, 0.9), 0.1);
+        // neither active, so no state is kept for them.
+        layout.pin(6, P2d(0.1, 0.1));
+        layout.set_target(7, P2d(0.1, 0.1), 0.1);
+        step_n(&mut layout, 3, 0.001);
+
+        assert_eq!(layout.slots.len(), subset.len());
+        assert!(layout.slots.capacity() < n);
+        assert_eq!(layout.forces.len(), subset.len());
+        assert_eq!(layout.calm_iterations.len(), subset.len());
+        assert_eq!(layout.incident.len(), subset.len());
+        assert_eq!(layout.restless.len(), subset.len());
+        assert_eq!(layout.velocities.len(), subset.len());
+        assert_eq!(layout.pinned.len(), subset.len());
+        assert_eq!(layout.node_bounds.len(), subset.len());
+        assert_eq!(layout.targets.len(), subset.len());
+        assert!(layout.is_pinned(500) && !layout.is_pinned(6));
+        let p = layout.positions()[5];
+        assert!(p.0 <= 0.5 && p.1 <= 0.5);
+    }
+
+    #[test]
+    fn higher_spring_exponent_pulls_distant_nodes_harder() {
+        let (p1, p2) = (P2d(0.0, 0.0), P2d(2.0, 0.0));
+        let pull = |exponent| attractive_force(&p1, &p2, 0.5, exponent).length_squared().sqrt();
+        assert!(pull(3.0) > pull(DEFAULT_SPRING_EXPONENT));
+        assert!(pull(DEFAULT_SPRING_EXPONENT) > pull(1.0));
+
+        // coincident nodes feel no force, for any exponent.
+        for &exponent in &[0.5, 1.0, 2.0] {
+            let force = attractive_force(&p1, &p1, 0.5, exponent);
+            assert_eq!((force.0, force.1), (0.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn total_velocity_decreases_as_the_layout_cools() {
+        let node_neighbors = ring(6);
+        let mut node_positions = random_positions(6, 13);
+        let mut layout = Layout::new(&mut node_positions, &node_neighbors).unwrap();
+        let velocities: Vec<f32> = (0..100)
+            .map(|iter| {
+                step_n(&mut layout, 1, 0.1 * (1.0 - iter as f32 / 100.0));
+                layout.total_velocity()
+            })
+            .collect();
+        // nodes held at the bounds move less, so it only decreases on
+        // average.
+        let means: Vec<f32> =
+            velocities.chunks(10).map(|v| v.iter().sum::<f32>() / 10.0).collect();
+        assert!(means.windows(2).all(|w| w[1] < w[0]), "{:?}", means);
+        assert!(means[9] < 0.1 * means[0]);
+    }
+
+    #[test]
+    fn strong_target_spring_pulls_node_to_its_target() {
+        let node_neighbors = ring(6);
+        let target = P2d(0.2, 0.8);
+        let distance = |strength: Option<f32>| {
+            let mut node_positions = random_positions(6, 3);
+            let mut layout = Layout::new(&mut node_positions, &node_neighbors).unwrap();
+            if let Some(strength) = strength {
+                layout.set_target(0, target, strength);
+            }
+            step_n(&mut layout, 100, 0.01);
+            layout.positions()[0].distance_squared_to_point(&target).sqrt()
+        };
+        let (free, anchored) = (distance(None), distance(Some(100.0)));
+        assert!(anchored < 0.05, "node ends {} from its target", anchored);
+        assert!(anchored < free);
+    }
+
+    // The adjacency lists of a `side` by `side` grid graph.
+    fn grid(side: usize) -> Vec<Vec<usize>> {
+        (0..side * side)
+            .map(|i| {
+                let (row, col) = (i / side, i % side);
+                let mut nb = Vec::new();
+                if col + 1 < side {
+                    nb.push(i + 1);
+                }
+                if row + 1 < side {
+                    nb.push(i + side);
+                }
+                nb
+            })
+            .collect()
+    }
+
+    // The mean distance of each node to its nearest other node.
+    fn mean_nearest_distance(node_positions: &[P2d]) -> f32 {
+        let nearest = |i: usize| {
+            (0..node_positions.len())
+                .filter(|&j| j != i)
+                .map(|j| node_positions[i].distance_squared_to_point(&node_positions[j]))
+                .fold(f32::INFINITY, f32::min)
+                .sqrt()
+        };
+        (0..node_positions.len()).map(nearest).sum::<f32>() / node_positions.len() as f32
+    }
+
+    #[test]
+    fn delaunay_repulsion_spaces_a_grid_like_full_repulsion() {
+        let side = 6;
+        let node_neighbors = grid(side);
+        let run = |delaunay_repulsion| {
+            let config = LayoutConfig { delaunay_repulsion, ..LayoutConfig::default() };
+            let mut node_positions = random_positions(side * side, 4);
+            let outcome =
+                layout_typical_2d_with(&config, &mut node_positions, &node_neighbors, 0).unwrap();
+            (node_positions, outcome)
+        };
+        let (full, full_outcome) = run(None);
+        let (delaunay, delaunay_outcome) = run(Some(1));
+        assert!(!full_outcome.approximate && delaunay_outcome.approximate);
+
+        let (full, delaunay) = (mean_nearest_distance(&full), mean_nearest_distance(&delaunay));
+        assert!(delaunay > 0.5 * full && delaunay < 2.0 * full,
+                "nearest distance {} with Delaunay, {} with full repulsion",
+                delaunay,
+                full);
+        // far fewer pairs to repel than all n (n - 1) / 2 of them.
+        let n = side * side;
+        assert!(4 * delaunay_edges(&random_positions(n, 4)).len() < n * (n - 1) / 2);
+    }
+
+    // The mean distance between all pairs of nodes.
+    fn mean_distance(node_positions: &[P2d]) -> f32 {
+        let n = node_positions.len();
+        let mut sum = 0.0;
+        for i in 0..n {
+            for j in i + 1..n {
+                sum += node_positions[i].distance_squared_to_point(&node_positions[j]).sqrt();
+            }
+        }
+        sum / (n * (n - 1) / 2) as f32
+    }
+
+    #[test]
+    fn doubled_repulsion_spreads_the_layout() {
+        let node_neighbors = ring(6);
+        let spread = |repulsion_strength| {
+            let config = LayoutConfig {
+                ideal_length: Some(0.05),
+                repulsion_strength,
+                ..LayoutConfig::default()
+            };
+            let mut node_positions = random_positions(6, 9);
+            let _ = layout_typical_2d_with(&config, &mut node_positions, &node_neighbors, 0)
+                .unwrap();
+            mean_distance(&node_positions)
+        };
+        let (single, double) = (spread(1.0), spread(2.0));
+        assert!(double > 1.1 * single, "mean distance {} -> {}", single, double);
+    }
+
+    #[test]
+    fn single_iteration_on_a_big_graph_does_not_converge() {
+        let node_neighbors = ring(200);
+        let config = LayoutConfig { max_iter: 1, ..LayoutConfig::default() };
+        let mut node_positions = random_positions(200, 6);
+        let outcome = layout_typical_2d_with(&config, &mut node_positions, &node_neighbors, 0)
+            .unwrap();
+        assert_eq!(outcome.iterations, 1);
+        assert!(!outcome.converged);
+    }
+
+    #[test]
+    fn exhausted_budget_makes_relax_a_no_op() {
+        let node_neighbors = ring(10);
+        let config = LayoutConfig {
+            max_iter: 30,
+            convergence: Convergence::DistanceMoved(0.0),
+            ..LayoutConfig::default()
+        };
+        let mut budget = RelaxBudget::new(50);
+        let mut node_positions = random_positions(10, 2);
+
+        let outcome = budget.relax(&config, &mut node_positions, &node_neighbors, 0).unwrap();
+        assert_eq!((outcome.iterations, budget.remaining()), (30, 20));
+        let outcome = budget.relax(&config, &mut node_positions, &node_neighbors, 0).unwrap();
+        assert_eq!((outcome.iterations, budget.remaining()), (20, 0));
+        assert!(budget.is_exhausted());
+
+        let before = node_positions.clone();
+        let outcome = budget.relax(&config, &mut node_positions, &node_neighbors, 0).unwrap();
+        assert_eq!(outcome.iterations, 0);
+        assert!(!outcome.converged);
+        for (p, q) in node_positions.iter().zip(before.iter()) {
+            assert_eq!((p.0, p.1), (q.0, q.1));
+        }
+    }
+
+    #[test]
+    fn tuned_ideal_length_gives_target_edge_length() {
+        let node_neighbors = ring(8);
+        let seed_positions = random_positions(8, 12);
+        let config = LayoutConfig::default();
+        let (target, tolerance) = (0.15, 0.01);
+        let l = tune_ideal_length(&config, &seed_positions, &node_neighbors, target, tolerance)
+            .unwrap();
+
+        let mut node_positions = seed_positions.clone();
+        let config = LayoutConfig { ideal_length: Some(l), ..config };
+        let _ = layout_typical_2d_with(&config, &mut node_positions, &node_neighbors, 0).unwrap();
+        let lengths = edge_lengths(&node_positions, &node_neighbors);
+        let average = lengths.iter().sum::<f32>() / lengths.len() as f32;
+        assert!((average - target).abs() <= tolerance,
+                "average edge length {} for l = {}",
+                average,
+                l);
+    }
+
+    #[test]
+    fn graph_just_over_the_threshold_is_approximated() {
+        let run = |n: usize| {
+            let config = LayoutConfig {
+                max_iter: 2,
+                approximate_above: Some(20),
+                ..LayoutConfig::default()
+            };
+            let mut node_positions = random_positions(n, 8);
+            layout_typical_2d_with(&config, &mut node_positions, &ring(n), 0).unwrap().approximate
+        };
+        assert!(!run(20));
+        assert!(run(21));
+
+        // the override keeps even large graphs exact.
+        let config = LayoutConfig {
+            max_iter: 2,
+            approximate_above: None,
+            ..LayoutConfig::default()
+        };
+        let mut node_positions = random_positions(DEFAULT_APPROXIMATE_ABOVE + 1, 8);
+        let outcome = layout_typical_2d_with(&config,
+                                             &mut node_positions,
+                                             &ring(DEFAULT_APPROXIMATE_ABOVE + 1),
+                                             0)
+            .unwrap();
+        assert!(!outcome.approximate);
+    }
+
+    #[test]
+    fn isolated_nodes_land_in_the_strip() {
+        // a ring of 6 nodes, and 4 isolated nodes, one with a self-loop.
+        let mut node_neighbors = ring(6);
+        node_neighbors.extend(vec![vec![], vec![7], vec![], vec![]]);
+        let height = 0.2;
+        let config = LayoutConfig { isolated_strip: Some(height), ..LayoutConfig::default() };
+        let mut node_positions = random_positions(10, 13);
+        let _ = layout_typical_2d_with(&config, &mut node_positions, &node_neighbors, 0).unwrap();
+
+        for pos in node_positions[6..].iter() {
+            assert!(pos.1 >= 1.0 - height && pos.1 <= 1.0);
+            assert!(pos.0 > 0.0 && pos.0 < 1.0, "isolated node clamped at x = {}", pos.0);
+        }
+        for pos in node_positions[..6].iter() {
+            assert!(pos.1 <= 1.0 - height);
+        }
+    }
+
+    #[test]
+    fn crowded_layout_grows_its_bounds() {
+        // a ring whose ideal length leaves it far too little room.
+        let node_neighbors = ring(30);
+        let run = |auto_grow_bounds| {
+            let config = LayoutConfig {
+                ideal_length: Some(0.4),
+                auto_grow_bounds,
+                ..LayoutConfig::default()
+            };
+            let mut node_positions = random_positions(30, 10);
+            let _ = layout_typical_2d_with(&config, &mut node_positions, &node_neighbors, 0)
+                .unwrap();
+            node_positions
+        };
+        // the nodes held at the outermost position reached, i.e. the bounds.
+        let piled = |node_positions: &[P2d]| {
+            let (min, max) = bounding_box(node_positions);
+            node_positions.iter()
+                          .filter(|pos| {
+                              pos.0 == min.0 || pos.0 == max.0 || pos.1 == min.1 || pos.1 == max.1
+                          })
+                          .count()
+        };
+
+        let clipped = run(false);
+        assert!(piled(&clipped) > 10);
+
+        let grown = run(true);
+        let (min, max) = bounding_box(&grown);
+        assert!(min.0 < 0.0 && min.1 < 0.0 && max.0 > 1.0 && max.1 > 1.0);
+        assert!(piled(&grown) < piled(&clipped),
+                "{} nodes piled up in grown bounds, {} in the unit square",
+                piled(&grown),
+                piled(&clipped));
+    }
+
+    #[test]
+    fn path_laid_out_in_1d_is_monotone() {
+        // a path of 12 nodes, numbered out of order along it.
+        let order = [3, 7, 0, 11, 5, 9, 1, 4, 10, 2, 8, 6];
+        let mut node_neighbors = vec![Vec::new(); order.len()];
+        for pair in order.windows(2) {
+            node_neighbors[pair[0]].push(pair[1]);
+        }
+        let xs = layout_1d(&node_neighbors, 50).unwrap();
+        assert_eq!(xs, layout_1d(&node_neighbors, 50).unwrap());
+        assert!(xs.iter().all(|&x| (0.0..=1.0).contains(&x)));
+
+        let along: Vec<f32> = order.iter().map(|&i| xs[i]).collect();
+        let increasing = along.windows(2).all(|w| w[0] < w[1]);
+        let decreasing = along.windows(2).all(|w| w[0] > w[1]);
+        assert!(increasing || decreasing, "{:?}", along);
+    }
+
+    #[test]
+    fn padding_keeps_clamped_nodes_inside() {
+        // too large an ideal length pushes the ring against the bounds.
+        let node_neighbors = ring(12);
+        let padding = 0.05;
+        let config = LayoutConfig {
+            ideal_length: Some(0.5),
+            padding,
+            ..LayoutConfig::default()
+        };
+        let mut node_positions = random_positions(12, 14);
+        let _ = layout_typical_2d_with(&config, &mut node_positions, &node_neighbors, 0).unwrap();
+        let at_bounds = node_positions.iter()
+                                      .filter(|pos| {
+                                          pos.0 == padding || pos.0 == 1.0 - padding ||
+                                          pos.1 == padding || pos.1 == 1.0 - padding
+                                      })
+                                      .count();
+        assert!(at_bounds > 0);
+        for pos in node_positions.iter() {
+            assert!(pos.0 >= padding && pos.0 <= 1.0 - padding);
+            assert!(pos.1 >= padding && pos.1 <= 1.0 - padding);
+        }
+    }
+
+    #[test]
+    fn self_loops_do_not_change_the_layout() {
+        let node_neighbors = ring(6);
+        let mut with_loops = node_neighbors.clone();
+        with_loops[2].push(2);
+        with_loops[4].insert(0, 4);
+        let run = |node_neighbors: &[Vec<usize>]| {
+            let mut node_positions = random_positions(6, 15);
+            let _ = layout_typical_2d(None, &mut node_positions, node_neighbors, 0).unwrap();
+            node_positions
+        };
+        for (p, q) in run(&node_neighbors).iter().zip(run(&with_loops).iter()) {
+            assert_eq!((p.0, p.1), (q.0, q.1));
+        }
+    }
+
+    #[test]
+    fn external_forces_buffer_gives_the_same_layout() {
+        let node_neighbors = ring(7);
+        let mut allocated = random_positions(7, 16);
+        let mut borrowed = allocated.clone();
+
+        let mut layout = Layout::new(&mut allocated, &node_neighbors).unwrap();
+        step_n(&mut layout, 20, 0.02);
+
+        let mut forces = vec![P2d(0.0, 0.0); 7];
+        let mut layout = Layout::with_forces(&mut borrowed, &node_neighbors, &mut forces).unwrap();
+        step_n(&mut layout, 20, 0.02);
+
+        for (p, q) in allocated.iter().zip(borrowed.iter()) {
+            assert_eq!((p.0, p.1), (q.0, q.1));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn forces_buffer_of_wrong_length_panics() {
+        let node_neighbors = ring(3);
+        let mut node_positions = random_positions(3, 16);
+        let mut forces = vec![P2d(0.0, 0.0); 2];
+        let _ = Layout::with_forces(&mut node_positions, &node_neighbors, &mut forces);
+    }
+
+    #[test]
+    fn best_of_five_is_no_worse_than_a_single_run() {
+        // a grid easily folds into a local minimum.
+        let node_neighbors = grid(4);
+        let config = LayoutConfig::default();
+        let (_, single) = layout_best_of(&config, &node_neighbors, 1, 17).unwrap();
+        let (best, energy) = layout_best_of(&config, &node_neighbors, 5, 17).unwrap();
+        assert!(energy <= single, "best of five {} > single {}", energy, single);
+
+        let l = default_ideal_length(16);
+        let recomputed =
+            energy_with_exponent(&best, &node_neighbors, l * l, l, config.spring_exponent);
+        assert!((energy - recomputed).abs() < 1e-3 * energy.abs());
+        assert_eq!(layout_best_of(&config, &node_neighbors, 5, 17).unwrap().1, energy);
+        assert!(layout_best_of(&config, &node_neighbors, 0, 17).is_err());
+    }
+
+    #[test]
+    fn small_and_large_graphs_settle_within_max_iter() {
+        // a ring of 5 nodes, and a binary tree of 500 nodes.
+        let tree: Vec<Vec<usize>> =
+            (0..500).map(|i| (2 * i + 1..2 * i + 3).filter(|&j| j < 500).collect()).collect();
+        for node_neighbors in [ring(5), tree] {
+            let n = node_neighbors.len();
+            let mut node_positions = random_positions(n, 18);
+            let outcome = layout_typical_2d(None, &mut node_positions, &node_neighbors, 0)
+                .unwrap();
+            assert!(outcome.converged,
+                    "{} nodes did not settle in {} iterations",
+                    n,
+                    outcome.iterations);
+            assert!(outcome.iterations <= DEFAULT_MAX_ITER);
+        }
+    }
+
+    // Parses a frame of `json_frame` into its iteration and positions.
+    fn parse_frame(frame: &str) -> Option<(usize, Vec<P2d>)> {
+        let rest = frame.strip_prefix(r#"{"iteration":"#)?;
+        let (iteration, rest) = rest.split_at(rest.find(',')?);
+        let positions = rest.strip_prefix(r#","positions":[["#)?.strip_suffix("]]}")?;
+        let positions = positions.split("],[")
+                                 .map(|pair| {
+                                     let (x, y) = pair.split_at(pair.find(',')?);
+                                     Some(P2d(x.parse().ok()?, y[1..].parse().ok()?))
+                                 })
+                                 .collect::<Option<Vec<P2d>>>()?;
+        Some((iteration.parse().ok()?, positions))
+    }
+
+    #[test]
+    fn streaming_sends_a_parsable_frame_per_iteration() {
+        let node_neighbors = ring(6);
+        let mut node_positions = random_positions(6, 19);
+        let mut frames = Vec::new();
+        let outcome = layout_streaming(&LayoutConfig::default(),
+                                       &mut node_positions,
+                                       &node_neighbors,
+                                       0,
+                                       &mut |frame| frames.push(frame.to_string()))
+            .unwrap();
+        assert_eq!(frames.len(), outcome.iterations);
+        for (k, frame) in frames.iter().enumerate() {
+            let (iteration, positions) = parse_frame(frame).expect(frame);
+            assert_eq!(iteration, k + 1);
+            assert_eq!(positions.len(), 6);
+        }
+        let (_, last) = parse_frame(frames.last().unwrap()).unwrap();
+        for (p, q) in last.iter().zip(node_positions.iter()) {
+            assert!((p.0 - q.0).abs() < 1e-6 && (p.1 - q.1).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn x_order_follows_the_ranks() {
+        // a timeline: a path of events, and some links back in time.
+        let n = 12;
+        let mut node_neighbors: Vec<Vec<usize>> =
+            (0..n).map(|i| if i + 1 < n { vec![i + 1] } else { vec![] }).collect();
+        node_neighbors[9].push(2);
+        node_neighbors[7].push(0);
+        let ranks: Vec<f32> = (0..n).map(|i| i as f32).collect();
+        let ordered_pairs = |x_order: Option<(Vec<f32>, f32)>| {
+            let config = LayoutConfig { x_order, ..LayoutConfig::default() };
+            let mut node_positions = random_positions(n, 20);
+            let _ = layout_typical_2d_with(&config, &mut node_positions, &node_neighbors, 0)
+                .unwrap();
+            let mut ordered = 0;
+            for i in 0..n {
+                for j in i + 1..n {
+                    if node_positions[i].0 <= node_positions[j].0 {
+                        ordered += 1;
+                    }
+                }
+            }
+            ordered
+        };
+        let pairs = n * (n - 1) / 2;
+        let (free, ranked) = (ordered_pairs(None), ordered_pairs(Some((ranks, 0.5))));
+        assert!(ranked as f32 >= 0.9 * pairs as f32, "{} of {} pairs ordered", ranked, pairs);
+        assert!(ranked > free);
+    }
+
+    #[test]
+    fn batch_equals_individual_layouts() {
+        let graphs = vec![(6, ring(6)),
+                          (3, vec![vec![1], vec![2], vec![]]),
+                          (9, grid(3)),
+                          (1, ring(1))];
+        let config = LayoutConfig::default();
+        let batch = layout_batch(&config, &graphs, 21).unwrap();
+        assert_eq!(batch.len(), graphs.len());
+        for (&(n, ref node_neighbors), batched) in graphs.iter().zip(batch.iter()) {
+            let (ref positions, outcome) = *batched;
+            let mut node_positions = random_positions(n, 21);
+            let expected = layout_typical_2d_with(&config, &mut node_positions, node_neighbors, 0)
+                .unwrap();
+            assert_eq!(outcome, expected);
+            assert_eq!(positions.len(), n);
+            for (p, q) in positions.iter().zip(node_positions.iter()) {
+                assert_eq!((p.0, p.1), (q.0, q.1));
+            }
+        }
+    }
 }
@@ -0,0 +1,208 @@
+//!
+//! Geometric helpers operating on laid out points.
+//!
+
+use std::cmp::Ordering;
+use super::P2d;
+
+// > 0 if `o`, `a`, `b` make a counter-clockwise turn.
+#[inline]
+fn cross(o: &P2d, a: &P2d, b: &P2d) -> f32 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+/// Whether the segments `a1`-`a2` and `b1`-`b2` cross, i.e. intersect in a
+/// single point in the interior of both. Segments which only touch, or are
+/// collinear, do not cross.
+pub fn segments_cross(a1: &P2d, a2: &P2d, b1: &P2d, b2: &P2d) -> bool {
+    let (d1, d2) = (cross(a1, a2, b1), cross(a1, a2, b2));
+    let (d3, d4) = (cross(b1, b2, a1), cross(b1, b2, a2));
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+/// The corners `(min, max)` of the smallest axis-aligned rectangle
+/// containing all `points`, which must not be empty.
+pub fn bounding_box(points: &[P2d]) -> (P2d, P2d) {
+    points.iter().fold((points[0], points[0]), |(min, max), pos| {
+        (P2d(min.0.min(pos.0), min.1.min(pos.1)), P2d(max.0.max(pos.0), max.1.max(pos.1)))
+    })
+}
+
+/// Computes the convex hull using Andrew's monotone chain algorithm.
+/// Returns the hull vertices in counter-clockwise order (with the y-axis
+/// pointing up), starting with the lowest leftmost point. Collinear points
+/// on the hull are omitted.
+pub fn convex_hull(points: &[P2d]) -> Vec<P2d> {
+    let mut sorted: Vec<P2d> = points.to_vec();
+    sorted.sort_by(|a, b| {
+        a.0
+         .partial_cmp(&b.0)
+         .unwrap_or(Ordering::Equal)
+         .then(a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+    });
+    sorted.dedup_by(|a, b| a.0 == b.0 && a.1 == b.1);
+
+    if sorted.len() < 3 {
+        return sorted;
+    }
+
+    let mut hull: Vec<P2d> = Vec::with_capacity(2 * sorted.len());
+
+    // lower hull
+    for p in sorted.iter() {
+        while hull.len() >= 2 && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], p) <= 0.0 {
+            hull.pop();
+        }
+        hull.push(*p);
+    }
+
+    // upper hull
+    let lower_len = hull.len() + 1;
+    for p in sorted.iter().rev().skip(1) {
+        while hull.len() >= lower_len &&
+              cross(&hull[hull.len() - 2], &hull[hull.len() - 1], p) <= 0.0 {
+            hull.pop();
+        }
+        hull.push(*p);
+    }
+
+    // the last point equals the first.
+    hull.pop();
+    hull
+}
+
+// A triangle of `delaunay_edges` by vertex index, with the center and
+// squared radius of its circumcircle.
+struct Triangle {
+    vertices: [usize; 3],
+    center: (f64, f64),
+    radius_squared: f64,
+}
+
+impl Triangle {
+    fn new(vertices: [usize; 3], points: &[(f64, f64)]) -> Triangle {
+        let (a, b, c) = (points[vertices[0]], points[vertices[1]], points[vertices[2]]);
+        let d = 2.0 * (a.0 * (b.1 - c.1) + b.0 * (c.1 - a.1) + c.0 * (a.1 - b.1));
+        let (a2, b2, c2) = (a.0 * a.0 + a.1 * a.1, b.0 * b.0 + b.1 * b.1, c.0 * c.0 + c.1 * c.1);
+        let center = ((a2 * (b.1 - c.1) + b2 * (c.1 - a.1) + c2 * (a.1 - b.1)) / d,
+                      (a2 * (c.0 - b.0) + b2 * (a.0 - c.0) + c2 * (b.0 - a.0)) / d);
+        // degenerate triangles have an infinite circumcircle.
+        let radius_squared = if d == 0.0 {
+            f64::INFINITY
+        } else {
+            (a.0 - center.0).powi(2) + (a.1 - center.1).powi(2)
+        };
+        Triangle {
+            vertices,
+            center,
+            radius_squared,
+        }
+    }
+
+    fn circumcircle_contains(&self, p: (f64, f64)) -> bool {
+        if self.radius_squared.is_infinite() {
+            return true;
+        }
+        (p.0 - self.center.0).powi(2) + (p.1 - self.center.1).powi(2) < self.radius_squared
+    }
+
+    // Whether the circumcircle lies entirely left of `p`.
+    fn left_of(&self, p: (f64, f64)) -> bool {
+        let dx = p.0 - self.center.0;
+        dx > 0.0 && dx * dx > self.radius_squared
+    }
+
+    fn edges(&self) -> [(usize, usize); 3] {
+        let [a, b, c] = self.vertices;
+        [(a.min(b), a.max(b)), (b.min(c), b.max(c)), (a.min(c), a.max(c))]
+    }
+}
+
+/// The edges of the Delaunay triangulation of `points`, as pairs of indices
+/// `(i, j)` with `i < j`, in ascending order. Uses the Bowyer-Watson
+/// algorithm, which takes quadratic time in the worst case. Collinear
+/// points are connected in a chain, and points coinciding with an earlier
+/// point may be left without edges.
+pub fn delaunay_edges(points: &[P2d]) -> Vec<(usize, usize)> {
+    let n = points.len();
+    if n < 2 {
+        return Vec::new();
+    }
+
+    let mut vertices: Vec<(f64, f64)> = points.iter().map(|p| (p.0 as f64, p.1 as f64)).collect();
+
+    // a triangle enclosing all points, far enough away not to disturb the
+    // triangulation.
+    let (mut min, mut max) = (vertices[0], vertices[0]);
+    for &(x, y) in vertices.iter() {
+        min = (min.0.min(x), min.1.min(y));
+        max = (max.0.max(x), max.1.max(y));
+    }
+    let size = (max.0 - min.0).max(max.1 - min.1).max(1.0);
+    let center = ((min.0 + max.0) / 2.0, (min.1 + max.1) / 2.0);
+    vertices.push((center.0 - 20.0 * size, center.1 - size));
+    vertices.push((center.0, center.1 + 20.0 * size));
+    vertices.push((center.0 + 20.0 * size, center.1 - size));
+
+    // inserting the points from left to right, a triangle whose
+    // circumcircle lies left of the current point is final.
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&a, &b| {
+        vertices[a].partial_cmp(&vertices[b]).unwrap_or(Ordering::Equal).then(a.cmp(&b))
+    });
+
+    let mut done: Vec<Triangle> = Vec::new();
+    let mut triangles = vec![Triangle::new([n, n + 1, n + 2], &vertices)];
+    for i in order {
+        let p = vertices[i];
+        let mut bad = Vec::new();
+        let mut open = Vec::with_capacity(triangles.len());
+        for t in triangles {
+            if t.circumcircle_contains(p) {
+                bad.push(t);
+            } else if t.left_of(p) {
+                done.push(t);
+            } else {
+                open.push(t);
+            }
+        }
+        triangles = open;
+
+        // the boundary of the hole left by the bad triangles consists of
+        // the edges which only one of them has.
+        let mut boundary: Vec<(usize, usize)> = Vec::new();
+        for edge in bad.iter().flat_map(|t| t.edges().to_vec()) {
+            match boundary.iter().position(|&e| e == edge) {
+                Some(pos) => {
+                    boundary.remove(pos);
+                }
+                None => boundary.push(edge),
+            }
+        }
+        for (a, b) in boundary {
+            triangles.push(Triangle::new([a, b, i], &vertices));
+        }
+    }
+
+    triangles.extend(done);
+    let mut edges: Vec<(usize, usize)> = triangles.iter()
+                                                  .flat_map(|t| t.edges().to_vec())
+                                                  .filter(|&(_, b)| b < n)
+                                                  .collect();
+    edges.sort();
+    edges.dedup();
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hull_of_square_with_interior_point_is_its_corners() {
+        let points = [P2d(1.0, 1.0), P2d(0.0, 1.0), P2d(0.5, 0.4), P2d(0.0, 0.0), P2d(1.0, 0.0)];
+        let hull = convex_hull(&points);
+        let corners: Vec<(f32, f32)> = hull.iter().map(|p| (p.0, p.1)).collect();
+        assert_eq!(corners, vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+    }
+}
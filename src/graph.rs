@@ -0,0 +1,174 @@
+//!
+//! A graph with nodes identified by arbitrary ids instead of indices, which
+//! keeps the mapping between both for its user.
+//!
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::hash::Hash;
+use super::{GraphLayoutError, P2d};
+use super::fruchterman_reingold::{self, LayoutConfig, LayoutOutcome};
+use super::initial::initial_positions_random;
+use super::rng::Rng;
+
+/// Nodes are added implicitly with the edges between them, or explicitly
+/// for isolated nodes. Each node gets the next free index.
+#[derive(Debug, Clone)]
+pub struct Graph<Id> {
+    // id of each node, by index.
+    ids: Vec<Id>,
+    // index of each node, by id.
+    indices: HashMap<Id, usize>,
+    node_neighbors: Vec<Vec<usize>>,
+}
+
+impl<Id> Default for Graph<Id>
+    where Id: Hash + Eq + Clone
+{
+    fn default() -> Graph<Id> {
+        Graph::new()
+    }
+}
+
+impl<Id> Graph<Id>
+    where Id: Hash + Eq + Clone
+{
+    pub fn new() -> Graph<Id> {
+        Graph {
+            ids: Vec::new(),
+            indices: HashMap::new(),
+            node_neighbors: Vec::new(),
+        }
+    }
+
+    /// Builds a graph of the edges `(src, dst)`.
+    pub fn from_edges<I>(edges: I) -> Graph<Id>
+        where I: IntoIterator<Item = (Id, Id)>
+    {
+        let mut graph = Graph::new();
+        for (src, dst) in edges {
+            graph.add_edge(src, dst);
+        }
+        graph
+    }
+
+    /// Returns the index of node `id`, which is added if it is new.
+    pub fn add_node(&mut self, id: Id) -> usize {
+        match self.indices.entry(id) {
+            Entry::Occupied(entry) => *entry.get(),
+            Entry::Vacant(entry) => {
+                let index = self.ids.len();
+                self.ids.push(entry.key().clone());
+                self.node_neighbors.push(Vec::new());
+                *entry.insert(index)
+            }
+        }
+    }
+
+    /// Adds an edge from `src` to `dst`, and the nodes if they are new.
+    pub fn add_edge(&mut self, src: Id, dst: Id) {
+        let src = self.add_node(src);
+        let dst = self.add_node(dst);
+        self.node_neighbors[src].push(dst);
+    }
+
+    /// The index of node `id`, if there is such a node.
+    pub fn index_of(&self, id: &Id) -> Option<usize> {
+        self.indices.get(id).cloned()
+    }
+
+    /// The id of each node, by index.
+    pub fn node_ids(&self) -> &[Id] {
+        &self.ids
+    }
+
+    /// The adjacency lists, by index, as expected by the layout functions.
+    pub fn node_neighbors(&self) -> &[Vec<usize>] {
+        &self.node_neighbors
+    }
+
+    /// Lays out the graph with `layout_typical_2d_with`, starting from
+    /// random positions drawn from `rng`. Returns the position of each
+    /// node by id, with the outcome of the layout.
+    pub fn layout(&self,
+                  config: &LayoutConfig,
+                  rng: &mut impl Rng)
+                  -> Result<(HashMap<Id, P2d>, LayoutOutcome), GraphLayoutError> {
+        let mut node_positions = initial_positions_random(self.ids.len(), rng);
+        let outcome = fruchterman_reingold::layout_typical_2d_with(config,
+                                                                   &mut node_positions,
+                                                                   &self.node_neighbors,
+                                                                   0)?;
+        Ok((self.by_id(node_positions), outcome))
+    }
+
+    /// Lays out the graph with `layout_incremental_2d`, keeping the nodes
+    /// in `previous` close to their positions there.
+    pub fn relayout(&self,
+                    config: &LayoutConfig,
+                    previous: &HashMap<Id, P2d>)
+                    -> Result<(HashMap<Id, P2d>, LayoutOutcome), GraphLayoutError> {
+        let (node_positions, outcome) =
+            fruchterman_reingold::layout_incremental_2d(config,
+                                                        previous,
+                                                        &self.ids,
+                                                        &self.node_neighbors)?;
+        Ok((self.by_id(node_positions), outcome))
+    }
+
+    // Keys `node_positions`, indexed by node, by the node ids.
+    fn by_id(&self, node_positions: Vec<P2d>) -> HashMap<Id, P2d> {
+        self.ids.iter().cloned().zip(node_positions).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use Vector;
+    use rng::XorShiftRng;
+
+    fn ring(n: usize) -> Graph<String> {
+        Graph::from_edges((0..n).map(|i| (format!("n{}", i), format!("n{}", (i + 1) % n))))
+    }
+
+    #[test]
+    fn relayout_keeps_unchanged_nodes_near() {
+        let config = LayoutConfig::default();
+        let graph = ring(8);
+        let (previous, _) = graph.layout(&config, &mut XorShiftRng::new(7)).unwrap();
+
+        let mut edited = graph.clone();
+        edited.add_edge("n0".to_string(), "new".to_string());
+        let (positions, _) = edited.relayout(&config, &previous).unwrap();
+
+        assert_eq!(positions.len(), 9);
+        assert!(positions.contains_key("new"));
+        for (id, before) in previous.iter() {
+            let after = &positions[id];
+            let moved = before.distance_squared_to_point(after).sqrt();
+            assert!(moved < 0.1, "{} moved by {}", id, moved);
+        }
+    }
+
+    #[test]
+    fn string_ids_round_trip_to_positions() {
+        let mut graph = Graph::from_edges(vec![("a", "b"), ("b", "c"), ("c", "a")]);
+        assert_eq!(graph.add_node("lonely"), 3);
+        assert_eq!(graph.add_node("b"), 1);
+        assert_eq!(graph.index_of(&"c"), Some(2));
+        assert_eq!(graph.index_of(&"d"), None);
+        assert_eq!(graph.node_ids(), &["a", "b", "c", "lonely"]);
+        assert_eq!(graph.node_neighbors(), &[vec![1], vec![2], vec![0], vec![]]);
+
+        let (positions, _) = graph.layout(&LayoutConfig::default(), &mut XorShiftRng::new(1))
+                                  .unwrap();
+        assert_eq!(positions.len(), 4);
+        for id in graph.node_ids() {
+            let pos = positions[id];
+            assert!(pos.0.is_finite() && pos.1.is_finite());
+        }
+        assert!(positions["a"].distance_squared_to_point(&positions["b"]) > 0.0);
+    }
+}
@@ -0,0 +1,76 @@
+use std::io::{self, Write};
+use super::P2d;
+use super::adjacency::{edges, undirected_edges};
+
+/// Writes the graph as GraphML, with the node positions stored in the
+/// `x` and `y` data keys, for import into tools like Gephi or yEd. If not
+/// `directed`, an edge stored in both directions is written once.
+pub fn write_graphml(wr: &mut dyn Write,
+                     node_positions: &[P2d],
+                     node_neighbors: &[Vec<usize>],
+                     directed: bool)
+                     -> io::Result<()> {
+    writeln!(wr, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(wr,
+             r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#)?;
+    writeln!(wr,
+             r#"<key id="x" for="node" attr.name="x" attr.type="float"/>"#)?;
+    writeln!(wr,
+             r#"<key id="y" for="node" attr.name="y" attr.type="float"/>"#)?;
+    writeln!(wr,
+             r#"<graph id="G" edgedefault="{}">"#,
+             if directed {
+                 "directed"
+             } else {
+                 "undirected"
+             })?;
+
+    for (i, pos) in node_positions.iter().enumerate() {
+        writeln!(wr,
+                 r#"<node id="n{}"><data key="x">{}</data><data key="y">{}</data></node>"#,
+                 i,
+                 pos.0,
+                 pos.1)?;
+    }
+
+    let edges: Vec<(usize, usize)> = if directed {
+        edges(node_neighbors).collect()
+    } else {
+        undirected_edges(node_neighbors).collect()
+    };
+    for (src, dst) in edges {
+        writeln!(wr, r#"<edge source="n{}" target="n{}"/>"#, src, dst)?;
+    }
+
+    writeln!(wr, "</graph>")?;
+    writeln!(wr, "</graphml>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graphml(node_neighbors: &[Vec<usize>], directed: bool) -> String {
+        let node_positions = [P2d(0.0, 0.0), P2d(1.0, 0.0), P2d(0.5, 0.75)];
+        let mut out = Vec::new();
+        write_graphml(&mut out, &node_positions, node_neighbors, directed).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn counts_nodes_edges_and_coordinate_keys() {
+        // a triangle, stored in both directions.
+        let node_neighbors = vec![vec![1, 2], vec![0, 2], vec![0, 1]];
+        let undirected = graphml(&node_neighbors, false);
+        assert!(undirected.starts_with("<?xml"));
+        assert!(undirected.trim_end().ends_with("</graphml>"));
+        assert_eq!(undirected.matches("<graph ").count(), 1);
+        assert_eq!(undirected.matches("</graph>").count(), 1);
+        assert!(undirected.contains(r#"<key id="x" for="node""#));
+        assert!(undirected.contains(r#"<key id="y" for="node""#));
+        assert_eq!(undirected.matches("<node ").count(), 3);
+        assert_eq!(undirected.matches(r#"<data key="x">"#).count(), 3);
+        assert_eq!(undirected.matches("<edge ").count(), 3);
+        assert_eq!(graphml(&node_neighbors, true).matches("<edge ").count(), 6);
+    }
+}
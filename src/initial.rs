@@ -0,0 +1,97 @@
+//!
+//! Initial positions to start a layout from.
+//!
+
+use super::P2d;
+use super::rng::Rng;
+
+/// Places `rows * cols` nodes row by row on a hexagonal lattice, starting
+/// at `origin`. Odd rows are offset by half a cell, so that each node is
+/// at distance `spacing` from its up to six neighbors. A good start for
+/// honeycomb-structured graphs.
+pub fn initial_positions_hex(rows: usize, cols: usize, spacing: f32, origin: P2d) -> Vec<P2d> {
+    let row_height = spacing * 3f32.sqrt() / 2.0;
+    let mut positions = Vec::with_capacity(rows * cols);
+    for row in 0..rows {
+        let offset = if row % 2 == 1 { 0.5 * spacing } else { 0.0 };
+        for col in 0..cols {
+            positions.push(P2d(origin.0 + offset + col as f32 * spacing,
+                               origin.1 + row as f32 * row_height));
+        }
+    }
+    positions
+}
+
+/// Places `num_nodes` nodes uniformly at random within the unit square.
+pub fn initial_positions_random(num_nodes: usize, rng: &mut impl Rng) -> Vec<P2d> {
+    (0..num_nodes).map(|_| P2d(rng.next_f32(), rng.next_f32())).collect()
+}
+
+/// Places node `i` of `num_nodes` at `f(i)`, e.g. computed from an
+/// attribute of the node.
+pub fn initial_positions_from(num_nodes: usize, f: impl Fn(usize) -> P2d) -> Vec<P2d> {
+    (0..num_nodes).map(f).collect()
+}
+
+/// Places node `i` at `x = xs[i]`, e.g. from `layout_1d`, and at a random
+/// `y` within `[0, 1]`, to warm-start a 2d layout.
+pub fn initial_positions_from_1d(xs: &[f32], rng: &mut impl Rng) -> Vec<P2d> {
+    xs.iter().map(|&x| P2d(x, rng.next_f32())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance(a: &P2d, b: &P2d) -> f32 {
+        ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+    }
+
+    #[test]
+    fn hex_rows_are_offset_by_half_a_cell() {
+        let (rows, cols, spacing) = (3, 4, 2.0);
+        let positions = initial_positions_hex(rows, cols, spacing, P2d(1.0, -1.0));
+        assert_eq!(positions.len(), rows * cols);
+        assert_eq!((positions[0].0, positions[0].1), (1.0, -1.0));
+        // along a row.
+        assert!((distance(&positions[0], &positions[1]) - spacing).abs() < 1e-5);
+        // the second row is offset by half a cell, the third is not.
+        assert!((positions[cols].0 - positions[0].0 - 0.5 * spacing).abs() < 1e-5);
+        assert!((positions[2 * cols].0 - positions[0].0).abs() < 1e-5);
+        // across rows, to both neighbors below.
+        assert!((distance(&positions[1], &positions[cols]) - spacing).abs() < 1e-5);
+        assert!((distance(&positions[1], &positions[cols + 1]) - spacing).abs() < 1e-5);
+    }
+
+    // Returns 0, 0.1, 0.2, ... in turn.
+    struct CountingRng(u32);
+
+    impl Rng for CountingRng {
+        fn next_f32(&mut self) -> f32 {
+            self.0 += 1;
+            (self.0 - 1) as f32 / 10.0
+        }
+    }
+
+    #[test]
+    fn counting_rng_gives_predictable_positions() {
+        let positions = initial_positions_random(3, &mut CountingRng(0));
+        let coords: Vec<(f32, f32)> = positions.iter().map(|pos| (pos.0, pos.1)).collect();
+        assert_eq!(coords, vec![(0.0, 0.1), (0.2, 0.3), (0.4, 0.5)]);
+
+        let positions = initial_positions_from_1d(&[0.7, 0.9], &mut CountingRng(2));
+        let coords: Vec<(f32, f32)> = positions.iter().map(|pos| (pos.0, pos.1)).collect();
+        assert_eq!(coords, vec![(0.7, 0.2), (0.9, 0.3)]);
+    }
+
+    #[test]
+    fn closure_positions_each_node_by_index() {
+        let sizes = [3.0, 1.0, 2.0];
+        let positions = initial_positions_from(sizes.len(), |i| P2d(i as f32, sizes[i]));
+        assert_eq!(positions.len(), 3);
+        for (i, pos) in positions.iter().enumerate() {
+            assert_eq!((pos.0, pos.1), (i as f32, sizes[i]));
+        }
+        assert!(initial_positions_from(0, |_| unreachable!()).is_empty());
+    }
+}
@@ -0,0 +1,39 @@
+use std::io::{self, Write};
+use super::P2d;
+
+// JSON has no representation of NaN and infinity, so they become `null`.
+fn number(v: f32) -> String {
+    if v.is_finite() { v.to_string() } else { "null".to_string() }
+}
+
+/// The positions as a JSON array of `[x, y]` arrays, indexed by node.
+pub fn positions_json(node_positions: &[P2d]) -> String {
+    let mut json = String::with_capacity(2 + 24 * node_positions.len());
+    json.push('[');
+    for (i, pos) in node_positions.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        json.push('[');
+        json.push_str(&number(pos.0));
+        json.push(',');
+        json.push_str(&number(pos.1));
+        json.push(']');
+    }
+    json.push(']');
+    json
+}
+
+/// A snapshot of a layout in progress, as a JSON object with the
+/// `iteration` and the `positions` as in `positions_json`, e.g. to send it
+/// to a live visualization.
+pub fn json_frame(iteration: usize, node_positions: &[P2d]) -> String {
+    format!(r#"{{"iteration":{},"positions":{}}}"#,
+            iteration,
+            positions_json(node_positions))
+}
+
+/// Writes the positions as in `positions_json`, followed by a newline.
+pub fn write_json(wr: &mut dyn Write, node_positions: &[P2d]) -> io::Result<()> {
+    writeln!(wr, "{}", positions_json(node_positions))
+}
@@ -1,7 +1,29 @@
+#[cfg(feature = "raster")]
+extern crate png;
+
 pub use vector::Vector;
-pub use p2d::P2d;
+pub use p2d::{P2d, positions_as_f32_slice};
+pub use vecn::VecN;
+pub use error::{GraphLayoutError, LayoutError};
+pub use bounds::Bounds;
 
 mod vector;
 mod p2d;
+mod vecn;
+mod error;
+mod bounds;
+pub mod adjacency;
+pub mod graph;
+pub mod geometry;
+pub mod spatial;
+pub mod metrics;
+pub mod transform;
+pub mod initial;
+pub mod rng;
 pub mod svg_writer;
+pub mod csv_writer;
+pub mod graphml_writer;
+pub mod json_writer;
+#[cfg(feature = "raster")]
+pub mod raster;
 pub mod fruchterman_reingold;
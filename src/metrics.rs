@@ -0,0 +1,255 @@
+//!
+//! Metrics to judge the quality of a layout.
+//!
+
+use std::cmp::Ordering;
+use super::{GraphLayoutError, P2d, Vector};
+use super::adjacency::{distances_from_each, edges, undirected_edges, validate};
+use super::geometry::{bounding_box, segments_cross};
+
+/// The euclidean length of each edge, in the order of `adjacency::edges`.
+pub fn edge_lengths(node_positions: &[P2d], node_neighbors: &[Vec<usize>]) -> Vec<f32> {
+    edges(node_neighbors)
+        .map(|(src, dst)| {
+            node_positions[src].distance_squared_to_point(&node_positions[dst]).sqrt()
+        })
+        .collect()
+}
+
+/// The `k` longest edges with their lengths, longest first. Overly long
+/// edges often point at badly placed nodes.
+pub fn longest_edges(node_positions: &[P2d],
+                     node_neighbors: &[Vec<usize>],
+                     k: usize)
+                     -> Vec<((usize, usize), f32)> {
+    let mut ranked: Vec<((usize, usize), f32)> =
+        edges(node_neighbors).zip(edge_lengths(node_positions, node_neighbors)).collect();
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+    ranked.truncate(k);
+    ranked
+}
+
+/// The stress of the layout, as minimized by multidimensional scaling:
+/// the sum of `(|p_i - p_j| - d_ij)^2` over all pairs of nodes `i < j`,
+/// where `d_ij` is the length of the shortest path in hops, treating edges
+/// as undirected. Pairs without a path are skipped. As an edge is meant to
+/// have length 1, scale a layout by the inverse of its ideal edge length
+/// first. Fails if the adjacency lists are invalid, see
+/// `adjacency::validate`.
+pub fn stress(node_positions: &[P2d],
+              node_neighbors: &[Vec<usize>])
+              -> Result<f32, GraphLayoutError> {
+    validate(node_positions.len(), node_neighbors)?;
+    let mut sum = 0.0;
+    distances_from_each(node_neighbors, |i, distances| {
+        for (j, d) in distances.iter().enumerate().skip(i + 1) {
+            if let Some(d) = *d {
+                let dist = node_positions[i].distance_squared_to_point(&node_positions[j]).sqrt();
+                let error = dist - d as f32;
+                sum += error * error;
+            }
+        }
+        true
+    });
+    Ok(sum)
+}
+
+/// The ratio of width to height of the bounding box of the layout, e.g. to
+/// size a canvas for it. `1` if there are no nodes, or all are at the same
+/// point, and infinite if they are on a horizontal line.
+pub fn aspect_ratio(node_positions: &[P2d]) -> f32 {
+    if node_positions.is_empty() {
+        return 1.0;
+    }
+    let (min, max) = bounding_box(node_positions);
+    let (width, height) = (max.0 - min.0, max.1 - min.1);
+    if height > 0.0 {
+        width / height
+    } else if width > 0.0 {
+        f32::INFINITY
+    } else {
+        1.0
+    }
+}
+
+/// The number of pairs of edges which cross, counting edges stored in both
+/// directions only once. Edges sharing a node never cross. Takes quadratic
+/// time in the number of edges.
+pub fn edge_crossings(node_positions: &[P2d], node_neighbors: &[Vec<usize>]) -> usize {
+    let edges: Vec<(usize, usize)> = undirected_edges(node_neighbors).collect();
+    let mut crossings = 0;
+    for (k, &(a1, a2)) in edges.iter().enumerate() {
+        for &(b1, b2) in edges[k + 1..].iter() {
+            if a1 == b1 || a1 == b2 || a2 == b1 || a2 == b2 {
+                continue;
+            }
+            if segments_cross(&node_positions[a1],
+                              &node_positions[a2],
+                              &node_positions[b1],
+                              &node_positions[b2]) {
+                crossings += 1;
+            }
+        }
+    }
+    crossings
+}
+
+/// The number of pairs of nodes whose circles of `radii` overlap.
+pub fn node_overlaps(node_positions: &[P2d], radii: &[f32]) -> usize {
+    assert_eq!(node_positions.len(), radii.len());
+    let mut overlaps = 0;
+    for (i, pos) in node_positions.iter().enumerate() {
+        for j in i + 1..node_positions.len() {
+            let min_distance = radii[i] + radii[j];
+            if pos.distance_squared_to_point(&node_positions[j]) < min_distance * min_distance {
+                overlaps += 1;
+            }
+        }
+    }
+    overlaps
+}
+
+/// The number of pairs of nodes whose axis-aligned boxes overlap, with the
+/// half-width and half-height of each box given in `half_sizes`.
+pub fn box_overlaps(node_positions: &[P2d], half_sizes: &[P2d]) -> usize {
+    assert_eq!(node_positions.len(), half_sizes.len());
+    let mut overlaps = 0;
+    for (i, pos) in node_positions.iter().enumerate() {
+        for j in i + 1..node_positions.len() {
+            if (pos.0 - node_positions[j].0).abs() < half_sizes[i].0 + half_sizes[j].0 &&
+               (pos.1 - node_positions[j].1).abs() < half_sizes[i].1 + half_sizes[j].1 {
+                overlaps += 1;
+            }
+        }
+    }
+    overlaps
+}
+
+/// A scorecard of a layout, e.g. to compare the results of different
+/// parameters. The edge lengths are `0` if there are no edges (apart from
+/// self-loops).
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutQuality {
+    /// See `edge_crossings`.
+    pub crossings: usize,
+    /// See `stress`.
+    pub stress: f32,
+    pub min_edge_len: f32,
+    pub max_edge_len: f32,
+    pub avg_edge_len: f32,
+    /// See `node_overlaps`.
+    pub node_overlaps: usize,
+}
+
+/// Computes all metrics of `LayoutQuality`, with nodes drawn as circles of
+/// `radii`. The edge lengths count edges stored in both directions once,
+/// and skip self-loops. As it computes all-pairs metrics, it takes
+/// quadratic time. Fails if the adjacency lists are invalid.
+pub fn evaluate_layout(node_positions: &[P2d],
+                       node_neighbors: &[Vec<usize>],
+                       radii: &[f32])
+                       -> Result<LayoutQuality, GraphLayoutError> {
+    let stress = stress(node_positions, node_neighbors)?;
+    let lengths: Vec<f32> =
+        undirected_edges(node_neighbors)
+            .filter(|&(src, dst)| src != dst)
+            .map(|(src, dst)| {
+                node_positions[src].distance_squared_to_point(&node_positions[dst]).sqrt()
+            })
+            .collect();
+    let (min, max, avg) = if lengths.is_empty() {
+        (0.0, 0.0, 0.0)
+    } else {
+        (lengths.iter().cloned().fold(f32::INFINITY, f32::min),
+         lengths.iter().cloned().fold(0.0, f32::max),
+         lengths.iter().sum::<f32>() / lengths.len() as f32)
+    };
+    Ok(LayoutQuality {
+        crossings: edge_crossings(node_positions, node_neighbors),
+        stress,
+        min_edge_len: min,
+        max_edge_len: max,
+        avg_edge_len: avg,
+        node_overlaps: node_overlaps(node_positions, radii),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edge_lengths_of_unit_triangle_are_equal() {
+        let node_positions = [P2d(0.0, 0.0), P2d(1.0, 0.0), P2d(0.5, 0.75f32.sqrt())];
+        let node_neighbors = vec![vec![1], vec![2], vec![0]];
+        let lengths = edge_lengths(&node_positions, &node_neighbors);
+        assert_eq!(lengths.len(), 3);
+        for length in lengths {
+            assert!((length - 1.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn stretched_edge_ranks_first() {
+        let node_positions = [P2d(0.0, 0.0), P2d(0.1, 0.0), P2d(0.1, 0.1), P2d(0.9, 0.9)];
+        let node_neighbors = vec![vec![1], vec![2], vec![3], vec![]];
+        let longest = longest_edges(&node_positions, &node_neighbors, 2);
+        assert_eq!(longest.len(), 2);
+        assert_eq!(longest[0].0, (2, 3));
+        assert!((longest[0].1 - 0.8 * 2f32.sqrt()).abs() < 1e-6);
+        assert!(longest[1].1 <= longest[0].1);
+    }
+
+    #[test]
+    fn layout_matching_path_lengths_has_no_stress() {
+        // a path laid out on a line, stored in both directions, and an
+        // isolated node, which has no path to the others.
+        let node_positions = [P2d(0.0, 0.0), P2d(1.0, 0.0), P2d(2.0, 0.0), P2d(5.0, 5.0)];
+        let node_neighbors = vec![vec![1], vec![0, 2], vec![1], vec![]];
+        assert!(stress(&node_positions, &node_neighbors).unwrap().abs() < 1e-6);
+
+        // moving the end of the path by 0.5 stresses two pairs.
+        let node_positions = [P2d(0.0, 0.0), P2d(1.0, 0.0), P2d(2.5, 0.0), P2d(5.0, 5.0)];
+        assert!((stress(&node_positions, &node_neighbors).unwrap() - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn stress_rejects_unknown_neighbor() {
+        let node_positions = [P2d(0.0, 0.0), P2d(1.0, 0.0)];
+        let node_neighbors = vec![vec![2], vec![]];
+        match stress(&node_positions, &node_neighbors) {
+            Err(GraphLayoutError::InvalidNeighbor { .. }) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(evaluate_layout(&node_positions, &node_neighbors, &[0.1, 0.1]).is_err());
+    }
+
+    #[test]
+    fn wide_layout_has_ratio_above_one() {
+        let node_positions = [P2d(0.0, 0.0), P2d(4.0, 1.0), P2d(2.0, 0.5)];
+        assert_eq!(aspect_ratio(&node_positions), 4.0);
+        assert_eq!(aspect_ratio(&[P2d(0.0, 0.0), P2d(3.0, 0.0)]), f32::INFINITY);
+        assert_eq!(aspect_ratio(&[P2d(1.0, 1.0)]), 1.0);
+        assert_eq!(aspect_ratio(&[]), 1.0);
+    }
+
+    #[test]
+    fn quality_matches_the_individual_metrics() {
+        // a unit square with crossing diagonals, stored in both directions,
+        // and a self-loop, which leaves the edge lengths alone.
+        let node_positions = [P2d(0.0, 0.0), P2d(1.0, 0.0), P2d(1.0, 1.0), P2d(0.0, 1.0)];
+        let node_neighbors = vec![vec![1, 2], vec![0, 2, 3], vec![3], vec![0, 3]];
+        let radii = [0.1, 0.1, 0.6, 0.6];
+        let quality = evaluate_layout(&node_positions, &node_neighbors, &radii).unwrap();
+
+        assert_eq!(quality.crossings, edge_crossings(&node_positions, &node_neighbors));
+        assert_eq!(quality.crossings, 1);
+        assert_eq!(quality.stress, stress(&node_positions, &node_neighbors).unwrap());
+        assert_eq!(quality.node_overlaps, node_overlaps(&node_positions, &radii));
+        assert_eq!(quality.node_overlaps, 1);
+        assert_eq!(quality.min_edge_len, 1.0);
+        assert!((quality.max_edge_len - 2f32.sqrt()).abs() < 1e-6);
+        // four sides and two diagonals.
+        assert!((quality.avg_edge_len - (4.0 + 2.0 * 2f32.sqrt()) / 6.0).abs() < 1e-6);
+    }
+}
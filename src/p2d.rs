@@ -1,8 +1,55 @@
 use super::vector::Vector;
 
+// `repr(C)` guarantees the layout `positions_as_f32_slice` relies on.
 #[derive(Debug, Copy, Clone)]
+#[repr(C)]
 pub struct P2d(pub f32, pub f32);
 
+/// Views `positions` as a flat slice of `2 * positions.len()` coordinates,
+/// `x0, y0, x1, y1, ...`, without copying, e.g. to upload them into a
+/// vertex buffer.
+pub fn positions_as_f32_slice(positions: &[P2d]) -> &[f32] {
+    // safe, as `P2d` consists of two `f32` without padding, so `n` of them
+    // are laid out like `2 * n` `f32`, with the same alignment.
+    unsafe { ::std::slice::from_raw_parts(positions.as_ptr() as *const f32, 2 * positions.len()) }
+}
+
+impl P2d {
+    /// Projects the point onto the boundary of the disk around `center`
+    /// with `radius`, if it lies outside of it.
+    pub fn clip_within_disk(&self, center: &P2d, radius: f32) -> P2d {
+        let offset = self.sub(center);
+        let distance = offset.length_squared().sqrt();
+        if distance <= radius {
+            *self
+        } else {
+            let mut p = *center;
+            p.add_scaled(radius / distance, &offset);
+            p
+        }
+    }
+
+    /// Replaces NaN components by the midpoint between `min` and `max`, and
+    /// infinite ones by the bound they point at.
+    pub fn sanitize(&self, min: &P2d, max: &P2d) -> P2d {
+        P2d(sanitize_component(self.0, min.0, max.0),
+            sanitize_component(self.1, min.1, max.1))
+    }
+}
+
+#[inline]
+fn sanitize_component(c: f32, min: f32, max: f32) -> f32 {
+    if c.is_nan() {
+        0.5 * (min + max)
+    } else if c == f32::INFINITY {
+        max
+    } else if c == f32::NEG_INFINITY {
+        min
+    } else {
+        c
+    }
+}
+
 impl Vector for P2d {
     type Scalar = f32;
 
@@ -32,20 +79,68 @@ impl Vector for P2d {
         self.1 += factor * other.1;
     }
 
+    // NaN components would pass the comparisons below unchanged.
     fn clip_within(&self, min: &Self, max: &Self) -> Self {
-        P2d(if self.0 < min.0 {
+        let p = self.sanitize(min, max);
+        P2d(if p.0 < min.0 {
                 min.0
-            } else if self.0 > max.0 {
+            } else if p.0 > max.0 {
                 max.0
             } else {
-                self.0
+                p.0
             },
-            if self.1 < min.1 {
+            if p.1 < min.1 {
                 min.1
-            } else if self.1 > max.1 {
+            } else if p.1 > max.1 {
                 max.1
             } else {
-                self.1
+                p.1
             })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clipping_nan_gives_finite_position() {
+        let (min, max) = (P2d(0.0, 0.0), P2d(1.0, 1.0));
+        let p = P2d(f32::NAN, 0.5).clip_within(&min, &max);
+        assert!(p.0.is_finite() && p.1.is_finite());
+        assert_eq!((p.0, p.1), (0.5, 0.5));
+
+        let p = P2d(f32::INFINITY, f32::NEG_INFINITY).sanitize(&min, &max);
+        assert_eq!((p.0, p.1), (1.0, 0.0));
+    }
+
+    #[test]
+    fn flat_slice_holds_the_coordinates_in_order() {
+        let positions = [P2d(0.5, -1.0), P2d(2.0, 3.5), P2d(-0.25, 0.0)];
+        let flat = positions_as_f32_slice(&positions);
+        assert_eq!(flat.len(), 2 * positions.len());
+        assert_eq!(flat, &[0.5, -1.0, 2.0, 3.5, -0.25, 0.0]);
+        assert!(positions_as_f32_slice(&[]).is_empty());
+    }
+
+    #[test]
+    fn zero_is_the_origin() {
+        let zero = P2d::zero();
+        assert_eq!((zero.0, zero.1), (0.0, 0.0));
+        let new = P2d::new();
+        assert_eq!((new.0, new.1), (zero.0, zero.1));
+        assert_eq!(zero.length_squared(), 0.0);
+    }
+
+    #[test]
+    fn point_outside_the_disk_is_projected_onto_its_edge() {
+        let center = P2d(1.0, 2.0);
+        let projected = P2d(4.0, 6.0).clip_within_disk(&center, 2.5);
+        // (3, 4) away from the center, scaled down to length 2.5.
+        assert!((projected.0 - 2.5).abs() < 1e-6 && (projected.1 - 4.0).abs() < 1e-6);
+        assert!((projected.distance_squared_to_point(&center).sqrt() - 2.5).abs() < 1e-6);
+
+        let inside = P2d(1.5, 1.0).clip_within_disk(&center, 2.5);
+        assert_eq!((inside.0, inside.1), (1.5, 1.0));
+    }
+}
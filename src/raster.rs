@@ -0,0 +1,220 @@
+//!
+//! Renders a layout within the unit square to a PNG image, without an
+//! external converter. Edges are drawn as gray lines and nodes as black
+//! disks on a white background, encoded as a grayscale image by the `png`
+//! crate. Only built with the `raster` feature.
+//!
+
+use std::io;
+use png;
+use super::{GraphLayoutError, P2d};
+use super::adjacency;
+
+const BACKGROUND: u8 = 255;
+const EDGE: u8 = 128;
+const NODE: u8 = 0;
+
+// The border around the unit square and the node radius, as fractions of
+// the image size, as in `SvgCanvas::default_for_unit_layout`.
+const BORDER: f32 = 0.04;
+const RADIUS: f32 = 0.01;
+
+// A grayscale image, one byte per pixel, row by row.
+struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    fn new(width: usize, height: usize) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![BACKGROUND; width * height],
+        }
+    }
+
+    // Sets the pixel at `(x, y)`, if it lies within the image.
+    fn plot(&mut self, x: i64, y: i64, value: u8) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            self.pixels[y as usize * self.width + x as usize] = value;
+        }
+    }
+
+    // Draws the part of the line from `p1` to `p2` within the image, with
+    // Bresenham's algorithm.
+    fn line(&mut self, p1: (f32, f32), p2: (f32, f32), value: u8) {
+        let ((x1, y1), (x2, y2)) = match self.clip(p1, p2) {
+            Some((p1, p2)) => (round(p1), round(p2)),
+            None => return,
+        };
+        let (dx, dy) = ((x2 - x1).abs(), -(y2 - y1).abs());
+        let (sx, sy) = ((x2 - x1).signum(), (y2 - y1).signum());
+        let (mut x, mut y) = (x1, y1);
+        let mut err = dx + dy;
+        loop {
+            self.plot(x, y, value);
+            if x == x2 && y == y2 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    // Clips the segment from `p1` to `p2` to the image, by Liang-Barsky.
+    // `None` if it lies outside of the image.
+    fn clip(&self, p1: (f32, f32), p2: (f32, f32)) -> Option<((f32, f32), (f32, f32))> {
+        let (dx, dy) = (p2.0 - p1.0, p2.1 - p1.1);
+        let (max_x, max_y) = ((self.width - 1) as f32, (self.height - 1) as f32);
+        let (mut t0, mut t1) = (0.0f32, 1.0f32);
+        // the segment enters the half plane `p * t <= q` where `p < 0`, and
+        // leaves it where `p > 0`.
+        for &(p, q) in [(-dx, p1.0), (dx, max_x - p1.0), (-dy, p1.1), (dy, max_y - p1.1)].iter() {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return None;
+                }
+            } else if p < 0.0 {
+                t0 = t0.max(q / p);
+            } else {
+                t1 = t1.min(q / p);
+            }
+        }
+        if t0 > t1 {
+            return None;
+        }
+        Some(((p1.0 + t0 * dx, p1.1 + t0 * dy), (p1.0 + t1 * dx, p1.1 + t1 * dy)))
+    }
+
+    fn disk(&mut self, center: (f32, f32), radius: i64, value: u8) {
+        let r = radius as f32;
+        if center.0 < -r || center.1 < -r || center.0 > self.width as f32 + r ||
+           center.1 > self.height as f32 + r {
+            return;
+        }
+        let (cx, cy) = round(center);
+        for y in -radius..radius + 1 {
+            for x in -radius..radius + 1 {
+                if x * x + y * y <= radius * radius {
+                    self.plot(cx + x, cy + y, value);
+                }
+            }
+        }
+    }
+}
+
+fn round((x, y): (f32, f32)) -> (i64, i64) {
+    (x.round() as i64, y.round() as i64)
+}
+
+/// Renders the layout to a PNG image of `width` by `height` pixels, with
+/// the unit square scaled to fit inside a small border. Nodes and edges
+/// outside of the unit square are cut off at the edge of the image. Returns
+/// the encoded image, or an error if the adjacency lists do not match the
+/// nodes, or a position is not finite.
+pub fn render_png(node_positions: &[P2d],
+                  node_neighbors: &[Vec<usize>],
+                  width: u32,
+                  height: u32)
+                  -> Result<Vec<u8>, GraphLayoutError> {
+    adjacency::validate(node_positions.len(), node_neighbors)?;
+    if width == 0 || height == 0 {
+        return Err(GraphLayoutError::InvalidParameter("width and height"));
+    }
+    if node_positions.iter().any(|pos| !(pos.0.is_finite() && pos.1.is_finite())) {
+        return Err(GraphLayoutError::InvalidParameter("node_positions"));
+    }
+    let mut image = Image::new(width as usize, height as usize);
+    let (w, h) = (width as f32, height as f32);
+    let pixel = |pos: &P2d| {
+        (w * BORDER + pos.0 * w * (1.0 - 2.0 * BORDER),
+         h * BORDER + pos.1 * h * (1.0 - 2.0 * BORDER))
+    };
+    for (src, dst) in adjacency::edges(node_neighbors) {
+        image.line(pixel(&node_positions[src]), pixel(&node_positions[dst]), EDGE);
+    }
+    let radius = (w.min(h) * RADIUS).round().max(1.0) as i64;
+    for pos in node_positions.iter() {
+        image.disk(pixel(pos), radius, NODE);
+    }
+    encode_png(&image).map_err(|err| GraphLayoutError::Io(io::Error::other(err)))
+}
+
+fn encode_png(image: &Image) -> Result<Vec<u8>, png::EncodingError> {
+    let mut png = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut png, image.width as u32, image.height as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&image.pixels)?;
+        writer.finish()?;
+    }
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Decodes `png` into its width, height and grayscale pixels.
+    fn decode(png: &[u8]) -> (u32, u32, Vec<u8>) {
+        let decoder = png::Decoder::new(png);
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(info.color_type, png::ColorType::Grayscale);
+        pixels.truncate(info.buffer_size());
+        (info.width, info.height, pixels)
+    }
+
+    #[test]
+    fn png_decodes_with_nodes_and_edges() {
+        let node_positions = [P2d(0.0, 0.0), P2d(1.0, 1.0), P2d(1.0, 0.0)];
+        let node_neighbors = vec![vec![1], vec![], vec![]];
+        let (width, height) = (120, 80);
+        let png = render_png(&node_positions, &node_neighbors, width, height).unwrap();
+        let (w, h, pixels) = decode(&png);
+        assert_eq!((w, h), (width, height));
+        assert_eq!(pixels.len(), (width * height) as usize);
+
+        let at = |x: f32, y: f32| {
+            let x = (x * width as f32).round() as usize;
+            let y = (y * height as f32).round() as usize;
+            pixels[y * width as usize + x]
+        };
+        // the nodes sit within the border.
+        let border = |v: f32| BORDER + v * (1.0 - 2.0 * BORDER);
+        assert_eq!(at(border(0.0), border(0.0)), NODE);
+        assert_eq!(at(border(1.0), border(1.0)), NODE);
+        assert_eq!(at(border(1.0), border(0.0)), NODE);
+        assert_eq!(at(border(0.5), border(0.5)), EDGE);
+        assert_eq!(at(border(0.5), border(0.9)), BACKGROUND);
+    }
+
+    #[test]
+    fn edges_leaving_the_image_are_clipped() {
+        let node_positions = [P2d(0.5, 0.5), P2d(-1e30, 3e29), P2d(0.5, 1e12)];
+        let node_neighbors = vec![vec![1, 2], vec![], vec![]];
+        let png = render_png(&node_positions, &node_neighbors, 50, 50).unwrap();
+        let (_, _, pixels) = decode(&png);
+        assert!(pixels.contains(&EDGE));
+
+        for &bad in [f32::INFINITY, f32::NAN].iter() {
+            let node_positions = [P2d(0.5, 0.5), P2d(bad, 0.5)];
+            match render_png(&node_positions, &[vec![1], vec![]], 50, 50) {
+                Err(GraphLayoutError::InvalidParameter("node_positions")) => {}
+                other => panic!("expected an invalid position, got {:?}", other.map(|_| ())),
+            }
+        }
+    }
+}
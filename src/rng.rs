@@ -0,0 +1,38 @@
+//!
+//! A small seedable pseudo random number generator (xorshift64*), so that
+//! randomized algorithms are reproducible without depending on external
+//! crates. Randomized algorithms accept any implementation of `Rng`.
+//!
+
+/// A source of random numbers.
+pub trait Rng {
+    /// Uniformly distributed in `[0, 1)`.
+    fn next_f32(&mut self) -> f32;
+}
+
+#[derive(Debug, Clone)]
+pub struct XorShiftRng {
+    state: u64,
+}
+
+impl XorShiftRng {
+    pub fn new(seed: u64) -> XorShiftRng {
+        // xorshift gets stuck on an all-zero state.
+        XorShiftRng { state: if seed == 0 { 0x9e37_79b9_7f4a_7c15 } else { seed } }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+impl Rng for XorShiftRng {
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
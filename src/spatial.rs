@@ -0,0 +1,102 @@
+//!
+//! A spatial hash grid to find nearby nodes without visiting all of them.
+//!
+
+use std::collections::HashMap;
+use super::P2d;
+
+/// Buckets points into square cells of `cell_size`. All points within
+/// distance `cell_size` of a point are in its cell or one of the eight
+/// cells around it.
+#[derive(Debug, Clone)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> SpatialGrid {
+        assert!(cell_size > 0.0);
+        SpatialGrid {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    /// Builds a grid containing each node at its position.
+    pub fn from_positions(cell_size: f32, node_positions: &[P2d]) -> SpatialGrid {
+        let mut grid = SpatialGrid::new(cell_size);
+        for (i, pos) in node_positions.iter().enumerate() {
+            grid.insert(i, pos);
+        }
+        grid
+    }
+
+    /// The cell containing `pos`.
+    pub fn cell_of(&self, pos: &P2d) -> (i32, i32) {
+        ((pos.0 / self.cell_size).floor() as i32, (pos.1 / self.cell_size).floor() as i32)
+    }
+
+    pub fn insert(&mut self, index: usize, pos: &P2d) {
+        let cell = self.cell_of(pos);
+        self.cells.entry(cell).or_default().push(index);
+    }
+
+    /// The indices in `cell`, in the order they were inserted.
+    pub fn cell(&self, cell: (i32, i32)) -> &[usize] {
+        self.cells.get(&cell).map_or(&[], |indices| &indices[..])
+    }
+
+    /// The indices in `cell` and the eight cells around it, row by row.
+    pub fn neighbors_within<'a>(&'a self, cell: (i32, i32)) -> impl Iterator<Item = usize> + 'a {
+        let (x, y) = cell;
+        (-1..2).flat_map(move |dy| (-1..2).map(move |dx| (x + dx, y + dy)))
+               .flat_map(move |cell| self.cell(cell).iter().cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_are_bucketed_by_cell() {
+        let positions = [P2d(0.05, 0.05), P2d(0.15, 0.05), P2d(0.07, 0.02), P2d(-0.05, 0.25)];
+        let grid = SpatialGrid::from_positions(0.1, &positions);
+        assert_eq!(grid.cell_of(&positions[0]), (0, 0));
+        assert_eq!(grid.cell_of(&positions[3]), (-1, 2));
+        assert_eq!(grid.cell((0, 0)), &[0, 2]);
+        assert_eq!(grid.cell((1, 0)), &[1]);
+        assert_eq!(grid.cell((-1, 2)), &[3]);
+        assert!(grid.cell((5, 5)).is_empty());
+    }
+
+    #[test]
+    fn neighborhood_covers_the_adjacent_cells() {
+        let positions = [P2d(0.05, 0.05), P2d(0.15, 0.15), P2d(-0.05, -0.05), P2d(0.25, 0.05)];
+        let grid = SpatialGrid::from_positions(0.1, &positions);
+        let mut near: Vec<usize> = grid.neighbors_within((0, 0)).collect();
+        near.sort();
+        assert_eq!(near, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn nearby_points_are_always_neighbors() {
+        let cell_size = 0.1;
+        let positions: Vec<P2d> = (0..50).map(|i| {
+                                             let t = i as f32 * 0.37;
+                                             P2d(t.sin() * 0.5, (1.3 * t).cos() * 0.5)
+                                         })
+                                         .collect();
+        let grid = SpatialGrid::from_positions(cell_size, &positions);
+        for (i, a) in positions.iter().enumerate() {
+            let near: Vec<usize> = grid.neighbors_within(grid.cell_of(a)).collect();
+            for (j, b) in positions.iter().enumerate() {
+                let (dx, dy) = (a.0 - b.0, a.1 - b.1);
+                if (dx * dx + dy * dy).sqrt() <= cell_size {
+                    assert!(near.contains(&j), "{} is missing from the neighbors of {}", j, i);
+                }
+            }
+        }
+    }
+}
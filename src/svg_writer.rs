@@ -1,6 +1,13 @@
-use std::io::Write;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::io::{self, Write};
+use super::GraphLayoutError;
 use super::P2d;
+use super::adjacency::{self, edges, undirected_edges};
+use super::geometry::{bounding_box, convex_hull};
+use super::metrics::edge_lengths;
 
+#[derive(Debug, Clone)]
 pub struct SvgCanvas {
     pub width: f32,
     pub height: f32,
@@ -13,6 +20,36 @@ pub struct SvgCanvas {
     pub stroke_width: f32,
     pub stroke_color: String,
     pub fill_color: String,
+    /// Round emitted coordinates to `decimals` decimal places, which keeps
+    /// them stable across frames of an animation and shrinks the output.
+    pub round_coordinates: bool,
+    pub decimals: u32,
+    /// Define the node shape once as a `<symbol>` in the header, and draw
+    /// nodes as `<use>` of it, which shrinks large files. Nodes with an
+    /// individual style are still drawn as full circles.
+    pub node_symbol: bool,
+    /// Let the y-axis of the layout point up, instead of down as in SVG.
+    pub flip_y: bool,
+    /// Draw edges over the nodes instead of behind them, e.g. to
+    /// highlight them.
+    pub edges_on_top: bool,
+    /// Omit the `<?xml ?>` declaration, so that the output can be inlined
+    /// into (X)HTML documents.
+    pub inline: bool,
+    /// The `viewBox` as `(min_x, min_y, width, height)`. Defaults to the
+    /// canvas including its border.
+    pub view_box: Option<(f32, f32, f32, f32)>,
+    /// Draw edges which are nearly horizontal as arcs, bending by the given
+    /// fraction of their length (see `SvgWriter::curved_edge`), so that
+    /// edges within a layer do not overlap.
+    pub arc_horizontal_edges: Option<f32>,
+    /// Draw a ring around each node, extending the radius by the given
+    /// width, in the given color, to make nodes stand out.
+    pub halo: Option<(f32, String)>,
+    /// Stroke color and width of the edges given to
+    /// `SvgWriter::highlighted_edges`.
+    pub highlight_color: String,
+    pub highlight_width: f32,
 }
 
 impl SvgCanvas {
@@ -30,75 +67,529 @@ impl SvgCanvas {
             stroke_width: 1.0,
             stroke_color: "black".to_string(),
             fill_color: "red".to_string(),
+            round_coordinates: false,
+            decimals: 2,
+            node_symbol: false,
+            flip_y: false,
+            edges_on_top: false,
+            inline: false,
+            view_box: None,
+            arc_horizontal_edges: None,
+            halo: None,
+            highlight_color: "orange".to_string(),
+            highlight_width: 3.0,
         }
     }
+
+    /// Scales the layout, so that its median edge is rendered `target_px`
+    /// long, and fits the canvas to the bounding box of the nodes. Keeps the
+    /// default scale if there are no edges of positive length.
+    pub fn for_target_edge_px(node_positions: &[P2d],
+                              node_neighbors: &[Vec<usize>],
+                              target_px: f32)
+                              -> SvgCanvas {
+        let mut canvas = SvgCanvas::default_for_unit_layout();
+        if node_positions.is_empty() {
+            return canvas;
+        }
+
+        let mut lengths = edge_lengths(node_positions, node_neighbors);
+        lengths.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        let scale = match lengths.get(lengths.len() / 2) {
+            Some(&median) if median > 0.0 => target_px / median,
+            _ => canvas.scalex,
+        };
+
+        let (min, max) = bounding_box(node_positions);
+        canvas.scalex = scale;
+        canvas.scaley = scale;
+        canvas.offsetx = -min.0 * scale;
+        canvas.offsety = -min.1 * scale;
+        canvas.width = (max.0 - min.0) * scale;
+        canvas.height = (max.1 - min.1) * scale;
+        canvas
+    }
+
+    /// Scales all dimensions by `factor`, e.g. for high-DPI output.
+    pub fn scaled(&self, factor: f32) -> SvgCanvas {
+        SvgCanvas {
+            width: self.width * factor,
+            height: self.height * factor,
+            border: self.border * factor,
+            radius: self.radius * factor,
+            scalex: self.scalex * factor,
+            scaley: self.scaley * factor,
+            offsetx: self.offsetx * factor,
+            offsety: self.offsety * factor,
+            stroke_width: self.stroke_width * factor,
+            halo: self.halo.as_ref().map(|&(width, ref color)| (width * factor, color.clone())),
+            highlight_width: self.highlight_width * factor,
+            ..self.clone()
+        }
+    }
+
+    fn round(&self, v: f32) -> f32 {
+        if self.round_coordinates {
+            let factor = 10f32.powi(self.decimals as i32);
+            (v * factor).round() / factor
+        } else {
+            v
+        }
+    }
+
+    /// The position `pos` of the layout is drawn at on the canvas, as used
+    /// by `SvgWriter` for nodes and edges, e.g. to overlay annotations.
+    pub fn to_screen(&self, pos: &P2d) -> P2d {
+        let (x, y) = self.transform(pos);
+        P2d(x, y)
+    }
+
+    /// The position of the layout drawn at `screen`, i.e. the inverse of
+    /// `to_screen` up to its rounding, e.g. to find the node clicked on. The
+    /// coordinate is `0` along an axis whose scale is zero.
+    pub fn to_world(&self, screen: &P2d) -> P2d {
+        let unscale = |v: f32, scale: f32| if scale != 0.0 { v / scale } else { 0.0 };
+        let y = screen.1 - self.border;
+        let y = if self.flip_y { self.height - y } else { y };
+        P2d(unscale(screen.0 - self.border - self.offsetx, self.scalex),
+            unscale(y - self.offsety, self.scaley))
+    }
+
+    fn transform(&self, pos: &P2d) -> (f32, f32) {
+        let y = (pos.1 * self.scaley) + self.offsety;
+        let y = if self.flip_y { self.height - y } else { y };
+        (self.round(self.border + (pos.0 * self.scalex) + self.offsetx),
+         self.round(self.border + y))
+    }
+}
+
+// Height of the margin for the title, relative to its font size.
+const TITLE_MARGIN: f32 = 2.0;
+
+// Edges whose rendered slope is at most this are nearly horizontal.
+const HORIZONTAL_SLOPE: f32 = 0.05;
+
+/// The stroke width of the heaviest edge drawn by
+/// `SvgWriter::draw_graph_weighted`, relative to the canvas stroke width.
+pub const MAX_WEIGHT_WIDTH: f32 = 4.0;
+
+// How far the two edges between a pair of nodes bend apart, if drawn
+// directed.
+const DIRECTED_BEND: f32 = 0.15;
+
+// Samples of the viridis colormap, evenly spaced over [0, 1].
+const VIRIDIS: [(u8, u8, u8); 9] = [(0x44, 0x01, 0x54),
+                                    (0x47, 0x2d, 0x7b),
+                                    (0x3b, 0x52, 0x8b),
+                                    (0x2c, 0x72, 0x8e),
+                                    (0x21, 0x91, 0x8c),
+                                    (0x28, 0xae, 0x80),
+                                    (0x5e, 0xc9, 0x62),
+                                    (0xad, 0xdc, 0x30),
+                                    (0xfd, 0xe7, 0x25)];
+
+/// Maps `value01` in [0, 1] to a color of the viridis colormap, from
+/// `#440154` (dark purple) at 0 to `#fde725` (yellow) at 1. Values outside
+/// are clamped.
+pub fn colormap(value01: f32) -> String {
+    let v = if value01.is_nan() {
+        0.0
+    } else {
+        value01.clamp(0.0, 1.0)
+    };
+    let pos = v * (VIRIDIS.len() - 1) as f32;
+    let i = (pos.floor() as usize).min(VIRIDIS.len() - 2);
+    let t = pos - i as f32;
+    let (r1, g1, b1) = VIRIDIS[i];
+    let (r2, g2, b2) = VIRIDIS[i + 1];
+    let lerp = |a: u8, b: u8| (a as f32 + t * (b as f32 - a as f32)).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", lerp(r1, r2), lerp(g1, g2), lerp(b1, b2))
+}
+
+// Escapes the characters with a special meaning in XML.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// SI prefixes from large to small, with their factors.
+const SI_PREFIXES: [(&str, f32); 7] = [("T", 1e12),
+                                       ("G", 1e9),
+                                       ("M", 1e6),
+                                       ("k", 1e3),
+                                       ("", 1.0),
+                                       ("m", 1e-3),
+                                       ("µ", 1e-6)];
+
+/// Formats `value` with three significant digits and an SI prefix, e.g.
+/// `1234.5` as `1.23k` and `0.05` as `50.0m`. Suitable to label edges with
+/// weights of varying magnitude.
+pub fn format_si(value: f32) -> String {
+    if value == 0.0 || !value.is_finite() {
+        return value.to_string();
+    }
+    let mut index = SI_PREFIXES.iter()
+                               .position(|&(_, factor)| value.abs() >= factor)
+                               .unwrap_or(SI_PREFIXES.len() - 1);
+    // rounding may carry over into the next prefix, e.g. `999.9` to `1000`.
+    if index > 0 && round_significant(value / SI_PREFIXES[index].1).abs() >= 1000.0 {
+        index -= 1;
+    }
+    let (prefix, factor) = SI_PREFIXES[index];
+    let scaled = value / factor;
+    format!("{:.*}{}", significant_decimals(round_significant(scaled)), scaled, prefix)
+}
+
+// The number of decimals to show three significant digits of `value`,
+// which is below `1000` in magnitude.
+fn significant_decimals(value: f32) -> usize {
+    if value.abs() >= 100.0 {
+        0
+    } else if value.abs() >= 10.0 {
+        1
+    } else {
+        2
+    }
+}
+
+// Rounds `value` to the decimals given by `significant_decimals`.
+fn round_significant(value: f32) -> f32 {
+    let factor = 10f32.powi(significant_decimals(value) as i32);
+    (value * factor).round() / factor
+}
+
+/// How `draw_edges` draws the edges of the graph.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EdgeMode {
+    /// Draws each stored edge as a line, i.e. an edge stored in both
+    /// directions is drawn twice.
+    AsStored,
+    /// Draws a single line between each pair of adjacent nodes.
+    UndirectedDedup,
+    /// Draws each stored edge with an arrow. Reciprocal edges (`a -> b` and
+    /// `b -> a`) are drawn as two opposite curves, so that both arrows are
+    /// visible.
+    Directed,
 }
 
 pub struct SvgWriter<'a> {
     canvas: SvgCanvas,
-    wr: &'a mut Write,
+    wr: &'a mut dyn Write,
+    tooltips: Option<&'a [String]>,
+    links: Option<&'a [Option<String>]>,
+    node_classes: Option<&'a [String]>,
+    highlighted_edges: Option<&'a [(usize, usize)]>,
+    // whether the header defines the arrow marker.
+    arrow_marker: bool,
+    // text and font size of the title.
+    title: Option<(&'a str, f32)>,
 }
 
 impl<'a> SvgWriter<'a> {
-    pub fn new<'b>(canvas: SvgCanvas, wr: &'b mut Write) -> SvgWriter<'b> {
+    pub fn new<'b>(canvas: SvgCanvas, wr: &'b mut dyn Write) -> SvgWriter<'b> {
         SvgWriter {
-            canvas: canvas,
-            wr: wr,
+            canvas,
+            wr,
+            tooltips: None,
+            links: None,
+            node_classes: None,
+            highlighted_edges: None,
+            arrow_marker: true,
+            title: None,
         }
     }
 
-    pub fn header(&mut self) {
+    /// Shows `tooltips[i]` when hovering over node `i` in drawings of the
+    /// whole graph, as `<title>` of its shape. Nodes without an entry, or
+    /// with an empty one, get no tooltip.
+    pub fn tooltips(mut self, tooltips: &'a [String]) -> SvgWriter<'a> {
+        self.tooltips = Some(tooltips);
+        self
+    }
+
+    /// Turns node `i` into a hyperlink to `links[i]` in drawings of the
+    /// whole graph, by wrapping its shape into an `<a>` element. Nodes
+    /// without an entry, or with `None`, are not linked.
+    pub fn links(mut self, links: &'a [Option<String>]) -> SvgWriter<'a> {
+        self.links = Some(links);
+        self
+    }
+
+    /// Sets the `class` attribute of the shape of node `i` to
+    /// `node_classes[i]` in drawings of the whole graph, for styling with
+    /// CSS. Nodes without an entry, or with an empty one, get no class.
+    pub fn node_classes(mut self, node_classes: &'a [String]) -> SvgWriter<'a> {
+        self.node_classes = Some(node_classes);
+        self
+    }
+
+    /// Draws the `(src, dst)` edges over the other edges in drawings of
+    /// the whole graph, in the highlight color and width of the canvas,
+    /// e.g. a spanning tree from `adjacency::spanning_tree`.
+    pub fn highlighted_edges(mut self, edges: &'a [(usize, usize)]) -> SvgWriter<'a> {
+        self.highlighted_edges = Some(edges);
+        self
+    }
+
+    /// Whether `header` defines the arrow marker, which directed edges and
+    /// force arrows refer to. On by default, but drawings of the whole
+    /// graph omit it, unless they draw directed edges.
+    pub fn arrow_marker(mut self, arrow_marker: bool) -> SvgWriter<'a> {
+        self.arrow_marker = arrow_marker;
+        self
+    }
+
+    /// Writes `text` with `font_size` centered above the drawing, in a
+    /// margin of twice the font size, which `header` adds to the top of
+    /// the `viewBox`.
+    pub fn title(mut self, text: &'a str, font_size: f32) -> SvgWriter<'a> {
+        self.title = Some((text, font_size));
+        self
+    }
+
+    pub fn header(&mut self) -> io::Result<()> {
+        if !self.canvas.inline {
+            writeln!(&mut self.wr, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        }
+        let (min_x, mut min_y, width, mut height) =
+            self.canvas.view_box.unwrap_or((0.0,
+                                            0.0,
+                                            self.canvas.width + 2.0 * self.canvas.border,
+                                            self.canvas.height + 2.0 * self.canvas.border));
+        if let Some((_, font_size)) = self.title {
+            min_y -= TITLE_MARGIN * font_size;
+            height += TITLE_MARGIN * font_size;
+        }
         writeln!(&mut self.wr,
-                 r#"<?xml version="1.0" encoding="UTF-8"?>
-                <svg xmlns="http://www.w3.org/2000/svg"
+                 r#"<svg xmlns="http://www.w3.org/2000/svg"
                 version="1.1" baseProfile="full"
                 width="100%" height="100%"
                 viewBox="{} {} {} {}">"#,
-                 0,
-                 0,
-                 self.canvas.width + 2.0 * self.canvas.border,
-                 self.canvas.height + 2.0 * self.canvas.border)
-            .unwrap();
-        writeln!(&mut self.wr, r##"
-                <defs>
-                <marker id="arrow" viewBox="0 0 10 10" refX="1" refY="5" markerUnits="strokeWidth" orient="auto"
-                markerWidth="8" markerHeight="6"><polyline points="0,0 10,5 0,10 1,5" fill="darkblue" /></marker>
-                </defs>"##).unwrap();
+                 min_x,
+                 min_y,
+                 width,
+                 height)?;
+        if let Some((text, font_size)) = self.title {
+            writeln!(&mut self.wr,
+                     r#"<text x="{}" y="{}" font-size="{}" text-anchor="middle" dominant-baseline="middle">{}</text>"#,
+                     min_x + 0.5 * width,
+                     min_y + 0.5 * TITLE_MARGIN * font_size,
+                     font_size,
+                     escape_xml(text))?;
+        }
+        if !self.arrow_marker && !self.canvas.node_symbol {
+            return Ok(());
+        }
+        writeln!(&mut self.wr, "<defs>")?;
+        if self.arrow_marker {
+            writeln!(&mut self.wr, r##"<marker id="arrow" viewBox="0 0 10 10" refX="1" refY="5" markerUnits="strokeWidth" orient="auto"
+                markerWidth="8" markerHeight="6"><polyline points="0,0 10,5 0,10 1,5" fill="darkblue" /></marker>"##)?;
+        }
+        if self.canvas.node_symbol {
+            writeln!(&mut self.wr,
+                     r#"<symbol id="node" overflow="visible"><circle r="{}" stroke="{}" stroke-width="{}" fill="{}" /></symbol>"#,
+                     self.canvas.radius,
+                     self.canvas.stroke_color,
+                     self.canvas.stroke_width,
+                     self.canvas.fill_color)?;
+        }
+        writeln!(&mut self.wr, "</defs>")
+    }
+
+    pub fn footer(&mut self) -> io::Result<()> {
+        writeln!(&mut self.wr, "</svg>")
     }
 
-    pub fn footer(&mut self) {
-        writeln!(&mut self.wr, "</svg>").unwrap();
+    pub fn node(&mut self, pos: &P2d) -> io::Result<()> {
+        self.node_shape(pos, None, None, None)
+    }
+
+    /// Like `node`, but with the given fill color.
+    pub fn node_filled(&mut self, pos: &P2d, fill_color: &str) -> io::Result<()> {
+        self.node_shape(pos, Some(fill_color), None, None)
+    }
+
+    // Draws node `index`, including the per-node extras the writer was
+    // configured with.
+    fn indexed_node(&mut self,
+                    index: usize,
+                    pos: &P2d,
+                    fill_color: Option<&str>)
+                    -> io::Result<()> {
+        let title = self.tooltips
+                        .and_then(|tooltips| tooltips.get(index))
+                        .filter(|title| !title.is_empty());
+        let link = self.links.and_then(|links| links.get(index)).and_then(|link| link.as_ref());
+        let class = self.node_classes
+                        .and_then(|classes| classes.get(index))
+                        .filter(|class| !class.is_empty());
+
+        if let Some(link) = link {
+            writeln!(&mut self.wr, r#"<a href="{}">"#, escape_xml(link))?;
+        }
+        self.node_shape(pos,
+                        fill_color,
+                        title.map(|title| title.as_str()),
+                        class.map(|class| class.as_str()))?;
+        if link.is_some() {
+            writeln!(&mut self.wr, "</a>")?;
+        }
+        Ok(())
+    }
+
+    // Draws a node filled with `fill_color`, or the canvas fill color.
+    fn node_shape(&mut self,
+                  pos: &P2d,
+                  fill_color: Option<&str>,
+                  title: Option<&str>,
+                  class: Option<&str>)
+                  -> io::Result<()> {
+        let (x, y) = self.canvas.transform(pos);
+        if let Some((width, ref color)) = self.canvas.halo {
+            writeln!(&mut self.wr,
+                     r#"<circle cx="{}" cy="{}" r="{}" fill="{}" stroke="none" />"#,
+                     x,
+                     y,
+                     self.canvas.radius + width,
+                     color)?;
+        }
+        let (element, attributes) = match fill_color {
+            None if self.canvas.node_symbol => {
+                ("use", format!(r##"href="#node" x="{}" y="{}""##, x, y))
+            }
+            _ => {
+                ("circle",
+                 format!(r#"cx="{}" cy="{}" r="{}" stroke="{}" stroke-width="{}" fill="{}""#,
+                         x,
+                         y,
+                         self.canvas.radius,
+                         self.canvas.stroke_color,
+                         self.canvas.stroke_width,
+                         fill_color.unwrap_or(&self.canvas.fill_color)))
+            }
+        };
+        let attributes = match class {
+            Some(class) => format!(r#"{} class="{}""#, attributes, escape_xml(class)),
+            None => attributes,
+        };
+        match title {
+            Some(title) => {
+                writeln!(&mut self.wr,
+                         "<{} {}><title>{}</title></{}>",
+                         element,
+                         attributes,
+                         escape_xml(title),
+                         element)
+            }
+            None => writeln!(&mut self.wr, "<{} {} />", element, attributes),
+        }
     }
 
-    pub fn node(&mut self, pos: &P2d) {
-        let x = self.canvas.border + (pos.0 * self.canvas.scalex) + self.canvas.offsetx;
-        let y = self.canvas.border + (pos.1 * self.canvas.scaley) + self.canvas.offsety;
+    pub fn edge(&mut self, pos1: &P2d, pos2: &P2d, directed: bool) -> io::Result<()> {
+        let (x1, y1) = self.canvas.transform(pos1);
+        let (x2, y2) = self.canvas.transform(pos2);
+
+        let dx = x2 - x1;
+        let dy = y2 - y1;
+
+        if dx.abs() < self.canvas.width.recip() && dy.abs() < self.canvas.height.recip() {
+            return Ok(());
+        }
+
+        if let Some(bend) = self.canvas.arc_horizontal_edges {
+            if dy.abs() <= HORIZONTAL_SLOPE * dx.abs() {
+                return self.curved_edge(pos1, pos2, directed, bend);
+            }
+        }
+
+        let mx = self.canvas.round(x1 + 0.5 * dx);
+        let my = self.canvas.round(y1 + 0.5 * dy);
+
+        let marker = if directed {
+            r##" marker-mid="url(#arrow)""##
+        } else {
+            ""
+        };
+
         writeln!(&mut self.wr,
-                 r#"<circle cx="{}" cy="{}" r="{}" stroke="{}" stroke-width="{}" fill="{}" />"#,
-                 x,
-                 y,
-                 self.canvas.radius,
+                 r#"<path d="M{} {} L{} {} L{} {}" stroke="{}" stroke-width="{}" {}/>"#,
+                 x1,
+                 y1,
+                 mx,
+                 my,
+                 x2,
+                 y2,
                  self.canvas.stroke_color,
                  self.canvas.stroke_width,
-                 self.canvas.fill_color)
-            .unwrap();
+                 marker)
     }
 
-    pub fn edge(&mut self, pos1: &P2d, pos2: &P2d, directed: bool) {
-        let x1 = self.canvas.border + (pos1.0 * self.canvas.scalex) + self.canvas.offsetx;
-        let y1 = self.canvas.border + (pos1.1 * self.canvas.scaley) + self.canvas.offsety;
-        let x2 = self.canvas.border + (pos2.0 * self.canvas.scalex) + self.canvas.offsetx;
-        let y2 = self.canvas.border + (pos2.1 * self.canvas.scaley) + self.canvas.offsety;
+    /// Draws the force on each node as an arrow from the node to the point
+    /// `scale * force` away from it, in layout coordinates, to see why nodes
+    /// move where they do. `forces` holds one force per node, e.g. from
+    /// `Layout::node_forces`. Nodes without force get no arrow.
+    pub fn force_arrows(&mut self,
+                        node_positions: &[P2d],
+                        forces: &[P2d],
+                        scale: f32)
+                        -> io::Result<()> {
+        for (pos, force) in node_positions.iter().zip(forces.iter()) {
+            let (x1, y1) = self.canvas.transform(pos);
+            let (x2, y2) = self.canvas.transform(&P2d(pos.0 + scale * force.0,
+                                                      pos.1 + scale * force.1));
+            if (x2 - x1).abs() < self.canvas.width.recip() &&
+               (y2 - y1).abs() < self.canvas.height.recip() {
+                continue;
+            }
+            writeln!(&mut self.wr,
+                     r##"<path class="force" d="M{} {} L{} {}" stroke="darkblue" stroke-width="{}" marker-end="url(#arrow)" />"##,
+                     x1,
+                     y1,
+                     x2,
+                     y2,
+                     self.canvas.stroke_width)?;
+        }
+        Ok(())
+    }
+
+    /// Draws the edge as a quadratic curve bending to the left (seen in
+    /// the direction from `pos1` to `pos2`) by `bend` times its length.
+    /// An edge in the opposite direction thus bends to the other side.
+    pub fn curved_edge(&mut self,
+                       pos1: &P2d,
+                       pos2: &P2d,
+                       directed: bool,
+                       bend: f32)
+                       -> io::Result<()> {
+        let (x1, y1) = self.canvas.transform(pos1);
+        let (x2, y2) = self.canvas.transform(pos2);
 
         let dx = x2 - x1;
         let dy = y2 - y1;
 
         if dx.abs() < self.canvas.width.recip() && dy.abs() < self.canvas.height.recip() {
-            return;
+            return Ok(());
         }
 
-        let mx = x1 + 0.5 * dx;
-        let my = y1 + 0.5 * dy;
+        // control point
+        let cx = x1 + 0.5 * dx + bend * dy;
+        let cy = y1 + 0.5 * dy - bend * dx;
+
+        // split the curve at its middle, so that marker-mid has a vertex to attach to.
+        let mx = self.canvas.round(0.25 * x1 + 0.5 * cx + 0.25 * x2);
+        let my = self.canvas.round(0.25 * y1 + 0.5 * cy + 0.25 * y2);
+        let (cx1, cy1) = (self.canvas.round(0.5 * (x1 + cx)), self.canvas.round(0.5 * (y1 + cy)));
+        let (cx2, cy2) = (self.canvas.round(0.5 * (cx + x2)), self.canvas.round(0.5 * (cy + y2)));
 
         let marker = if directed {
             r##" marker-mid="url(#arrow)""##
@@ -107,38 +598,1056 @@ impl<'a> SvgWriter<'a> {
         };
 
         writeln!(&mut self.wr,
-                 r#"<path d="M{} {} L{} {} L{} {}" stroke="{}" stroke-width="{}" {}/>"#,
+                 r#"<path d="M{} {} Q{} {} {} {} Q{} {} {} {}" fill="none" stroke="{}" stroke-width="{}" {}/>"#,
                  x1,
                  y1,
+                 cx1,
+                 cy1,
                  mx,
                  my,
+                 cx2,
+                 cy2,
                  x2,
                  y2,
                  self.canvas.stroke_color,
                  self.canvas.stroke_width,
                  marker)
-            .unwrap();
     }
 
+    /// Writes `label` next to the middle of the edge, which bends by
+    /// `bend` as in `curved_edge`, or by `arc_horizontal_edges` if it is
+    /// straight and nearly horizontal.
+    pub fn edge_label(&mut self, pos1: &P2d, pos2: &P2d, bend: f32, label: &str) -> io::Result<()> {
+        let (x1, y1) = self.canvas.transform(pos1);
+        let (x2, y2) = self.canvas.transform(pos2);
+        let (dx, dy) = (x2 - x1, y2 - y1);
+
+        let bend = match self.canvas.arc_horizontal_edges {
+            Some(arc) if bend == 0.0 && dy.abs() <= HORIZONTAL_SLOPE * dx.abs() => arc,
+            _ => bend,
+        };
+        writeln!(&mut self.wr,
+                 r#"<text x="{}" y="{}" font-size="{}" text-anchor="middle" dominant-baseline="middle">{}</text>"#,
+                 self.canvas.round(x1 + 0.5 * dx + 0.5 * bend * dy),
+                 self.canvas.round(y1 + 0.5 * dy - 0.5 * bend * dx),
+                 self.canvas.radius,
+                 escape_xml(label))
+    }
+
+    /// Draws a translucent polygon around the convex hull of the `members`
+    /// nodes, padded by the node radius. Call it before drawing the nodes,
+    /// so that it is rendered behind them. Returns an error if a member is
+    /// not a node.
+    pub fn cluster_hull(&mut self,
+                        members: &[usize],
+                        node_positions: &[P2d],
+                        fill: &str,
+                        opacity: f32)
+                        -> Result<(), GraphLayoutError> {
+        if let Some(&node) = members.iter().find(|&&i| i >= node_positions.len()) {
+            return Err(GraphLayoutError::InvalidNode { node });
+        }
+        let hull = convex_hull(&members.iter()
+                                       .map(|&i| node_positions[i])
+                                       .collect::<Vec<_>>());
+        if hull.is_empty() {
+            return Ok(());
+        }
+
+        let points: Vec<String> = hull.iter()
+                                      .map(|pos| {
+                                          let (x, y) = self.canvas.transform(pos);
+                                          format!("{},{}", x, y)
+                                      })
+                                      .collect();
+
+        writeln!(&mut self.wr,
+                 r#"<polygon points="{}" fill="{}" stroke="{}" stroke-width="{}" stroke-linejoin="round" opacity="{}" />"#,
+                 points.join(" "),
+                 fill,
+                 fill,
+                 2.0 * self.canvas.radius,
+                 opacity)?;
+        Ok(())
+    }
+
+    /// Draws an overview of all nodes, scaled to fit into a square of
+    /// `size` with its top-left corner at `corner`, given in SVG
+    /// coordinates.
+    pub fn minimap(&mut self, node_positions: &[P2d], corner: &P2d, size: f32) -> io::Result<()> {
+        writeln!(&mut self.wr,
+                 r#"<rect x="{}" y="{}" width="{}" height="{}" fill="white" stroke="{}" stroke-width="{}" />"#,
+                 corner.0,
+                 corner.1,
+                 size,
+                 size,
+                 self.canvas.stroke_color,
+                 self.canvas.stroke_width)?;
+
+        if node_positions.is_empty() {
+            return Ok(());
+        }
+
+        let (min, max) = bounding_box(node_positions);
+        let extent = (max.0 - min.0).max(max.1 - min.1);
+        let scale = if extent > 0.0 { size / extent } else { 0.0 };
+        let radius = (size / 100.0).max(1.0);
+
+        for pos in node_positions.iter() {
+            let y = if self.canvas.flip_y {
+                max.1 - pos.1
+            } else {
+                pos.1 - min.1
+            };
+            writeln!(&mut self.wr,
+                     r#"<circle cx="{}" cy="{}" r="{}" fill="{}" />"#,
+                     self.canvas.round(corner.0 + (pos.0 - min.0) * scale),
+                     self.canvas.round(corner.1 + y * scale),
+                     radius,
+                     self.canvas.fill_color)?;
+        }
+        Ok(())
+    }
+
+    /// Draws all edges as specified by `mode`.
+    pub fn draw_edges(&mut self,
+                      node_positions: &[P2d],
+                      node_neighbors: &[Vec<usize>],
+                      mode: EdgeMode)
+                      -> io::Result<()> {
+        match mode {
+            EdgeMode::UndirectedDedup => {
+                for (src, dst) in undirected_edges(node_neighbors) {
+                    self.mode_edge(node_positions, node_neighbors, src, dst, mode)?;
+                }
+            }
+            EdgeMode::AsStored | EdgeMode::Directed => {
+                for (src, dst) in edges(node_neighbors) {
+                    self.mode_edge(node_positions, node_neighbors, src, dst, mode)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Draws the edge from `src` to `dst` as specified by `mode`: directed
+    // edges between the same pair of nodes bend apart.
+    fn mode_edge(&mut self,
+                 node_positions: &[P2d],
+                 node_neighbors: &[Vec<usize>],
+                 src: usize,
+                 dst: usize,
+                 mode: EdgeMode)
+                 -> io::Result<()> {
+        let pos1 = &node_positions[src];
+        let pos2 = &node_positions[dst];
+        if mode != EdgeMode::Directed {
+            self.edge(pos1, pos2, false)
+        } else if src != dst && node_neighbors[dst].contains(&src) {
+            self.curved_edge(pos1, pos2, true, DIRECTED_BEND)
+        } else {
+            self.edge(pos1, pos2, true)
+        }
+    }
+
+    // Like `draw_edges`, but draws edge `k` in the order of `edges` with
+    // stroke width `widths[k]`, which also scales its arrow.
+    fn draw_edges_with_widths(&mut self,
+                              node_positions: &[P2d],
+                              node_neighbors: &[Vec<usize>],
+                              mode: EdgeMode,
+                              widths: &[f32])
+                              -> io::Result<()> {
+        let stroke_width = self.canvas.stroke_width;
+        let mut seen = HashSet::new();
+        for ((src, dst), &width) in edges(node_neighbors).zip(widths.iter()) {
+            if mode == EdgeMode::UndirectedDedup && !seen.insert((src.min(dst), src.max(dst))) {
+                continue;
+            }
+            self.canvas.stroke_width = width;
+            let result = self.mode_edge(node_positions, node_neighbors, src, dst, mode);
+            self.canvas.stroke_width = stroke_width;
+            result?;
+        }
+        Ok(())
+    }
+
+    /// See `draw_edges` for how edges are drawn.
     pub fn draw_graph(mut self,
                       node_positions: &[P2d],
                       node_neighbors: &[Vec<usize>],
-                      directed: bool) {
-        self.header();
-
-        // start with the edges
-        for (i, pos1) in node_positions.iter().enumerate() {
-            for &n in node_neighbors[i].iter() {
-                let pos2 = &node_positions[n];
-                self.edge(&pos1, &pos2, directed);
+                      mode: EdgeMode)
+                      -> Result<(), GraphLayoutError> {
+        self.validate(node_positions, node_neighbors)?;
+        self.graph_header(mode == EdgeMode::Directed)?;
+        self.layers(node_positions,
+                    None,
+                    |wr| wr.draw_edges(node_positions, node_neighbors, mode))?;
+        self.footer()?;
+        Ok(())
+    }
+
+    // Like `header`, but omits the arrow marker for drawings without
+    // `directed` edges.
+    fn graph_header(&mut self, directed: bool) -> io::Result<()> {
+        self.arrow_marker = self.arrow_marker && directed;
+        self.header()
+    }
+
+    /// Like `draw_graph` with `EdgeMode::AsStored`, but takes the positions
+    /// of the nodes, and the end points of the edges, from iterators, so
+    /// that huge graphs can be generated lazily instead of held in memory.
+    /// The output is written as it is generated, and flushed at the end.
+    /// Highlighted edges are not drawn, as they refer to nodes by index.
+    pub fn draw_graph_streamed<N, E>(mut self, nodes: N, edges: E) -> Result<(), GraphLayoutError>
+        where N: IntoIterator<Item = P2d>,
+              E: IntoIterator<Item = (P2d, P2d)>
+    {
+        self.graph_header(false)?;
+        if self.canvas.edges_on_top {
+            self.streamed_nodes(nodes)?;
+            self.streamed_edges(edges)?;
+        } else {
+            self.streamed_edges(edges)?;
+            self.streamed_nodes(nodes)?;
+        }
+        self.footer()?;
+        self.wr.flush()?;
+        Ok(())
+    }
+
+    fn streamed_edges<E>(&mut self, edges: E) -> io::Result<()>
+        where E: IntoIterator<Item = (P2d, P2d)>
+    {
+        writeln!(&mut self.wr, r#"<g class="edges">"#)?;
+        for (pos1, pos2) in edges {
+            self.edge(&pos1, &pos2, false)?;
+        }
+        writeln!(&mut self.wr, "</g>")
+    }
+
+    fn streamed_nodes<N>(&mut self, nodes: N) -> io::Result<()>
+        where N: IntoIterator<Item = P2d>
+    {
+        writeln!(&mut self.wr, r#"<g class="nodes">"#)?;
+        for (i, pos) in nodes.into_iter().enumerate() {
+            self.indexed_node(i, &pos, None)?;
+        }
+        writeln!(&mut self.wr, "</g>")
+    }
+
+    // Draws the edges with `draw_edges` and the nodes, filled with
+    // `fill_colors` if given, each layer in a group of the same class.
+    fn layers<E>(&mut self,
+                 node_positions: &[P2d],
+                 fill_colors: Option<&[String]>,
+                 draw_edges: E)
+                 -> io::Result<()>
+        where E: Fn(&mut Self) -> io::Result<()>
+    {
+        // edges go behind the nodes, unless asked otherwise.
+        if !self.canvas.edges_on_top {
+            writeln!(&mut self.wr, r#"<g class="edges">"#)?;
+            draw_edges(self)?;
+            writeln!(&mut self.wr, "</g>")?;
+            self.highlighted(node_positions)?;
+        }
+
+        writeln!(&mut self.wr, r#"<g class="nodes">"#)?;
+        for (i, pos) in node_positions.iter().enumerate() {
+            let fill_color = fill_colors.map(|colors| colors[i].as_str());
+            self.indexed_node(i, pos, fill_color)?;
+        }
+        writeln!(&mut self.wr, "</g>")?;
+
+        if self.canvas.edges_on_top {
+            writeln!(&mut self.wr, r#"<g class="edges">"#)?;
+            draw_edges(self)?;
+            writeln!(&mut self.wr, "</g>")?;
+            self.highlighted(node_positions)?;
+        }
+        Ok(())
+    }
+
+    // Checks the graph, and that the highlighted edges are between its nodes.
+    fn validate(&self,
+                node_positions: &[P2d],
+                node_neighbors: &[Vec<usize>])
+                -> Result<(), GraphLayoutError> {
+        let n = node_positions.len();
+        adjacency::validate(n, node_neighbors)?;
+        let invalid = self.highlighted_edges
+                          .unwrap_or(&[])
+                          .iter()
+                          .flat_map(|&(src, dst)| vec![src, dst])
+                          .find(|&node| node >= n);
+        match invalid {
+            Some(node) => Err(GraphLayoutError::InvalidNode { node }),
+            None => Ok(()),
+        }
+    }
+
+    // Draws the highlighted edges, if any, in a group of their own.
+    fn highlighted(&mut self, node_positions: &[P2d]) -> io::Result<()> {
+        let edges = match self.highlighted_edges {
+            Some(edges) => edges,
+            None => return Ok(()),
+        };
+        writeln!(&mut self.wr, r#"<g class="highlighted">"#)?;
+        for &(src, dst) in edges.iter() {
+            let (x1, y1) = self.canvas.transform(&node_positions[src]);
+            let (x2, y2) = self.canvas.transform(&node_positions[dst]);
+            writeln!(&mut self.wr,
+                     r#"<path d="M{} {} L{} {}" stroke="{}" stroke-width="{}" />"#,
+                     x1,
+                     y1,
+                     x2,
+                     y2,
+                     self.canvas.highlight_color,
+                     self.canvas.highlight_width)?;
+        }
+        writeln!(&mut self.wr, "</g>")
+    }
+
+    /// Draws all edges, undirected, as a single `<path>` element, which
+    /// keeps files of large graphs small.
+    pub fn edges_batched(&mut self,
+                         node_positions: &[P2d],
+                         node_neighbors: &[Vec<usize>])
+                         -> io::Result<()> {
+        let mut d = String::new();
+        for (src, dst) in edges(node_neighbors) {
+            let (x1, y1) = self.canvas.transform(&node_positions[src]);
+            let (x2, y2) = self.canvas.transform(&node_positions[dst]);
+
+            if (x2 - x1).abs() < self.canvas.width.recip() &&
+               (y2 - y1).abs() < self.canvas.height.recip() {
+                continue;
+            }
+
+            if !d.is_empty() {
+                d.push(' ');
+            }
+            d.push_str(&format!("M{} {} L{} {}", x1, y1, x2, y2));
+        }
+
+        if d.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(&mut self.wr,
+                 r#"<path d="{}" fill="none" stroke="{}" stroke-width="{}" />"#,
+                 d,
+                 self.canvas.stroke_color,
+                 self.canvas.stroke_width)
+    }
+
+    /// Like `draw_graph` for undirected graphs, but draws all edges as
+    /// a single path.
+    pub fn draw_graph_batched(mut self,
+                              node_positions: &[P2d],
+                              node_neighbors: &[Vec<usize>])
+                              -> Result<(), GraphLayoutError> {
+        self.validate(node_positions, node_neighbors)?;
+        self.graph_header(false)?;
+        self.layers(node_positions,
+                    None,
+                    |wr| wr.edges_batched(node_positions, node_neighbors))?;
+        self.footer()?;
+        Ok(())
+    }
+
+    /// Like `draw_graph`, but fills each node with the `colormap` color
+    /// of its value, after normalizing `values` to [0, 1].
+    pub fn draw_graph_colored(mut self,
+                              node_positions: &[P2d],
+                              node_neighbors: &[Vec<usize>],
+                              mode: EdgeMode,
+                              values: &[f32])
+                              -> Result<(), GraphLayoutError> {
+        self.validate(node_positions, node_neighbors)?;
+        if values.len() != node_positions.len() {
+            return Err(GraphLayoutError::LengthMismatch {
+                expected: node_positions.len(),
+                actual: values.len(),
+            });
+        }
+        let min = values.iter().cloned().fold(f32::INFINITY, f32::min);
+        let max = values.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        let range = max - min;
+        let fill_colors: Vec<String> = values.iter()
+                                             .map(|&value| {
+                                                 let value01 = if range > 0.0 {
+                                                     (value - min) / range
+                                                 } else {
+                                                     0.0
+                                                 };
+                                                 colormap(value01)
+                                             })
+                                             .collect();
+
+        self.graph_header(mode == EdgeMode::Directed)?;
+        self.layers(node_positions,
+                    Some(&fill_colors),
+                    |wr| wr.draw_edges(node_positions, node_neighbors, mode))?;
+        self.footer()?;
+        Ok(())
+    }
+
+    /// Like `draw_graph`, but draws each edge with a stroke width in
+    /// proportion to its weight, the heaviest `MAX_WEIGHT_WIDTH` times the
+    /// stroke width of the canvas. As the arrow markers scale with the
+    /// stroke width, heavier directed edges get larger arrows. `weights`
+    /// holds one non-negative weight per edge, in the order of
+    /// `adjacency::edges`.
+    pub fn draw_graph_weighted(mut self,
+                               node_positions: &[P2d],
+                               node_neighbors: &[Vec<usize>],
+                               mode: EdgeMode,
+                               weights: &[f32])
+                               -> Result<(), GraphLayoutError> {
+        self.validate(node_positions, node_neighbors)?;
+        let num_edges = edges(node_neighbors).count();
+        if weights.len() != num_edges {
+            return Err(GraphLayoutError::LengthMismatch {
+                expected: num_edges,
+                actual: weights.len(),
+            });
+        }
+        if weights.iter().any(|&weight| !(weight.is_finite() && weight >= 0.0)) {
+            return Err(GraphLayoutError::InvalidParameter("weights"));
+        }
+
+        let max = weights.iter().cloned().fold(0.0, f32::max);
+        let stroke_width = self.canvas.stroke_width;
+        let widths: Vec<f32> = weights.iter()
+                                      .map(|&weight| if max > 0.0 {
+                                          MAX_WEIGHT_WIDTH * stroke_width * weight / max
+                                      } else {
+                                          stroke_width
+                                      })
+                                      .collect();
+
+        self.graph_header(mode == EdgeMode::Directed)?;
+        self.layers(node_positions,
+                    None,
+                    |wr| wr.draw_edges_with_widths(node_positions, node_neighbors, mode, &widths))?;
+        self.footer()?;
+        Ok(())
+    }
+
+    /// Like `draw_graph`, but labels each edge with its weight, formatted
+    /// by `format`, e.g. `format_si` or `|w| format!("{:.2}", w)`. `weights`
+    /// holds one weight per edge, in the order of `adjacency::edges`.
+    pub fn draw_graph_weighted_labeled<F>(mut self,
+                                          node_positions: &[P2d],
+                                          node_neighbors: &[Vec<usize>],
+                                          mode: EdgeMode,
+                                          weights: &[f32],
+                                          format: F)
+                                          -> Result<(), GraphLayoutError>
+        where F: Fn(f32) -> String
+    {
+        self.validate(node_positions, node_neighbors)?;
+        let num_edges = edges(node_neighbors).count();
+        if weights.len() != num_edges {
+            return Err(GraphLayoutError::LengthMismatch {
+                expected: num_edges,
+                actual: weights.len(),
+            });
+        }
+
+        self.graph_header(mode == EdgeMode::Directed)?;
+        self.layers(node_positions,
+                    None,
+                    |wr| wr.draw_edges(node_positions, node_neighbors, mode))?;
+
+        // label each drawn edge once, where `draw_edges` drew it.
+        writeln!(&mut self.wr, r#"<g class="labels">"#)?;
+        let mut seen = HashSet::new();
+        for ((src, dst), &weight) in edges(node_neighbors).zip(weights.iter()) {
+            if mode == EdgeMode::UndirectedDedup && !seen.insert((src.min(dst), src.max(dst))) {
+                continue;
+            }
+            let bend = if mode == EdgeMode::Directed && src != dst &&
+                          node_neighbors[dst].contains(&src) {
+                DIRECTED_BEND
+            } else {
+                0.0
+            };
+            self.edge_label(&node_positions[src], &node_positions[dst], bend, &format(weight))?;
+        }
+        writeln!(&mut self.wr, "</g>")?;
+
+        self.footer()?;
+        Ok(())
+    }
+
+    /// Draws an animation of the layout moving through `frames`, e.g. the
+    /// positions after each iteration, at `fps` frames per second. Nodes
+    /// and (undirected) edges are animated using SMIL `<animate>` elements,
+    /// the animation stops at the last frame.
+    pub fn draw_animated_graph(mut self,
+                               frames: &[Vec<P2d>],
+                               node_neighbors: &[Vec<usize>],
+                               fps: f32)
+                               -> Result<(), GraphLayoutError> {
+        if frames.is_empty() {
+            return Err(GraphLayoutError::InvalidParameter("frames"));
+        }
+        if !(fps.is_finite() && fps > 0.0) {
+            return Err(GraphLayoutError::InvalidParameter("fps"));
+        }
+        let n = frames[0].len();
+        adjacency::validate(n, node_neighbors)?;
+        if let Some(frame) = frames.iter().find(|frame| frame.len() != n) {
+            return Err(GraphLayoutError::LengthMismatch {
+                expected: n,
+                actual: frame.len(),
+            });
+        }
+
+        let dur = frames.len() as f32 / fps;
+        // coordinates of each node in each frame.
+        let mut xs = vec![Vec::with_capacity(frames.len()); n];
+        let mut ys = vec![Vec::with_capacity(frames.len()); n];
+        for frame in frames.iter() {
+            for (i, pos) in frame.iter().enumerate() {
+                let (x, y) = self.canvas.transform(pos);
+                xs[i].push(x);
+                ys[i].push(y);
             }
         }
 
-        // then with the nodes.
-        for pos1 in node_positions.iter() {
-            self.node(&pos1);
+        self.graph_header(false)?;
+        for (src, dst) in edges(node_neighbors) {
+            writeln!(&mut self.wr,
+                     r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}" stroke-width="{}">"#,
+                     xs[src][0],
+                     ys[src][0],
+                     xs[dst][0],
+                     ys[dst][0],
+                     self.canvas.stroke_color,
+                     self.canvas.stroke_width)?;
+            self.animate("x1", &xs[src], dur)?;
+            self.animate("y1", &ys[src], dur)?;
+            self.animate("x2", &xs[dst], dur)?;
+            self.animate("y2", &ys[dst], dur)?;
+            writeln!(&mut self.wr, "</line>")?;
         }
+        for (x, y) in xs.iter().zip(ys.iter()) {
+            writeln!(&mut self.wr,
+                     r#"<circle cx="{}" cy="{}" r="{}" stroke="{}" stroke-width="{}" fill="{}">"#,
+                     x[0],
+                     y[0],
+                     self.canvas.radius,
+                     self.canvas.stroke_color,
+                     self.canvas.stroke_width,
+                     self.canvas.fill_color)?;
+            self.animate("cx", x, dur)?;
+            self.animate("cy", y, dur)?;
+            writeln!(&mut self.wr, "</circle>")?;
+        }
+        self.footer()?;
+        Ok(())
+    }
+
+    // Animates `attribute` of the enclosing element through `values`.
+    fn animate(&mut self, attribute: &str, values: &[f32], dur: f32) -> io::Result<()> {
+        let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+        writeln!(&mut self.wr,
+                 r#"<animate attributeName="{}" values="{}" dur="{}s" fill="freeze" />"#,
+                 attribute,
+                 values.join(";"),
+                 dur)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The output of `draw`, given a writer on `canvas`.
+    fn render<F>(canvas: SvgCanvas, draw: F) -> String
+        where F: FnOnce(SvgWriter) -> Result<(), GraphLayoutError>
+    {
+        let mut out = Vec::new();
+        draw(SvgWriter::new(canvas, &mut out)).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn paths(svg: &str) -> Vec<&str> {
+        svg.lines().filter(|line| line.starts_with("<path")).collect()
+    }
+
+    // The value of attribute `name` in the element `line`.
+    fn attr(line: &str, name: &str) -> f32 {
+        let start = line.find(&format!(" {}=\"", name)).unwrap() + name.len() + 3;
+        line[start..].split('"').next().unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn reciprocal_directed_edges_are_two_curves_with_arrows() {
+        let node_positions = [P2d(0.2, 0.2), P2d(0.8, 0.6)];
+        let node_neighbors = vec![vec![1], vec![0]];
+        let svg = render(SvgCanvas::default_for_unit_layout(), |wr| {
+            wr.draw_graph(&node_positions, &node_neighbors, EdgeMode::Directed)
+        });
+        let paths = paths(&svg);
+        assert_eq!(paths.len(), 2);
+        assert_ne!(paths[0], paths[1]);
+        for path in paths {
+            assert!(path.contains(" Q"));
+            assert!(path.contains("marker-mid=\"url(#arrow)\""));
+        }
+        assert!(svg.contains("<marker id=\"arrow\""));
+    }
+
+    #[test]
+    fn scaled_by_two_doubles_width_and_radius() {
+        let canvas = SvgCanvas::default_for_unit_layout();
+        let scaled = canvas.scaled(2.0);
+        assert_eq!(scaled.width, 2.0 * canvas.width);
+        assert_eq!(scaled.radius, 2.0 * canvas.radius);
+        assert_eq!(scaled.to_screen(&P2d(0.5, 0.5)).0,
+                   2.0 * canvas.to_screen(&P2d(0.5, 0.5)).0);
+    }
+
+    #[test]
+    fn cluster_hull_is_a_polygon_with_fill_and_opacity() {
+        let node_positions = [P2d(0.1, 0.1), P2d(0.9, 0.1), P2d(0.5, 0.8), P2d(0.5, 0.5)];
+        let svg = render(SvgCanvas::default_for_unit_layout(),
+                         |mut wr| wr.cluster_hull(&[0, 1, 2], &node_positions, "teal", 0.25));
+        assert!(svg.starts_with("<polygon "));
+        assert!(svg.contains("fill=\"teal\""));
+        assert!(svg.contains("opacity=\"0.25\""));
+
+        let mut out = Vec::new();
+        let mut wr = SvgWriter::new(SvgCanvas::default_for_unit_layout(), &mut out);
+        match wr.cluster_hull(&[0, 4], &node_positions, "teal", 0.25) {
+            Err(GraphLayoutError::InvalidNode { node: 4 }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn batched_edges_are_one_path() {
+        let node_positions = [P2d(0.1, 0.1), P2d(0.9, 0.1), P2d(0.9, 0.9), P2d(0.1, 0.9)];
+        let node_neighbors = vec![vec![1], vec![2], vec![3], vec![0]];
+        let svg = render(SvgCanvas::default_for_unit_layout(),
+                         |wr| wr.draw_graph_batched(&node_positions, &node_neighbors));
+        let paths = paths(&svg);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].matches('M').count(), 4);
+    }
+
+    #[test]
+    fn rounded_coordinates_have_at_most_the_configured_decimals() {
+        let node_positions = [P2d(0.123_456, 0.654_321), P2d(0.987_654, 0.333_333)];
+        let node_neighbors = vec![vec![1], vec![]];
+        let canvas = SvgCanvas {
+            round_coordinates: true,
+            decimals: 2,
+            ..SvgCanvas::default_for_unit_layout()
+        };
+        let svg = render(canvas, |wr| {
+            wr.draw_graph(&node_positions, &node_neighbors, EdgeMode::AsStored)
+        });
+        let numbers = svg.split(|c: char| !(c.is_ascii_digit() || c == '.'));
+        for number in numbers.filter(|number| number.contains('.')) {
+            assert!(number.len() - number.find('.').unwrap() - 1 <= 2, "{}", number);
+        }
+    }
+
+    #[test]
+    fn minimap_has_a_small_marker_per_node_within_its_region() {
+        let node_positions = [P2d(0.1, 0.1), P2d(0.9, 0.2), P2d(0.4, 0.8), P2d(0.6, 0.5)];
+        let svg = render(SvgCanvas::default_for_unit_layout(), |mut wr| {
+            wr.minimap(&node_positions, &P2d(10.0, 10.0), 100.0)?;
+            Ok(())
+        });
+        let markers: Vec<&str> = svg.lines().filter(|line| line.starts_with("<circle")).collect();
+        assert_eq!(markers.len(), node_positions.len());
+        for marker in markers {
+            assert!((10.0..=110.0).contains(&attr(marker, "cx")));
+            assert!((10.0..=110.0).contains(&attr(marker, "cy")));
+            assert!(attr(marker, "r") < SvgCanvas::default_for_unit_layout().radius);
+        }
+    }
+
+    #[test]
+    fn colormap_endpoints() {
+        assert_eq!(colormap(0.0), "#440154");
+        assert_eq!(colormap(1.0), "#fde725");
+        assert_eq!(colormap(-1.0), "#440154");
+        assert_eq!(colormap(2.0), "#fde725");
+    }
+
+    #[test]
+    fn symbol_mode_defines_one_symbol_and_uses_it_per_node() {
+        let node_positions = [P2d(0.1, 0.1), P2d(0.9, 0.1), P2d(0.5, 0.8)];
+        let node_neighbors = vec![vec![1], vec![2], vec![0]];
+        let canvas = SvgCanvas { node_symbol: true, ..SvgCanvas::default_for_unit_layout() };
+        let svg = render(canvas, |wr| {
+            wr.draw_graph(&node_positions, &node_neighbors, EdgeMode::AsStored)
+        });
+        assert_eq!(svg.matches("<symbol").count(), 1);
+        assert_eq!(svg.matches("<use ").count(), node_positions.len());
+        assert_eq!(svg.matches("<circle").count(), 1);
+    }
+
+    #[test]
+    fn flip_y_draws_the_top_of_the_layout_at_the_top() {
+        // node 1 is at the top of the y-up layout.
+        let node_positions = [P2d(0.5, 0.0), P2d(0.5, 1.0)];
+        let canvas = SvgCanvas { flip_y: true, ..SvgCanvas::default_for_unit_layout() };
+        let svg = render(canvas, |wr| {
+            wr.draw_graph(&node_positions, &[vec![], vec![]], EdgeMode::AsStored)
+        });
+        let nodes: Vec<&str> = svg.lines().filter(|line| line.starts_with("<circle")).collect();
+        assert_eq!(nodes.len(), 2);
+        assert!(attr(nodes[1], "cy") < attr(nodes[0], "cy"));
+        assert_eq!(attr(nodes[1], "cy"), SvgCanvas::default_for_unit_layout().border);
+    }
+
+    #[test]
+    fn edges_on_top_come_after_the_nodes() {
+        let node_positions = [P2d(0.1, 0.1), P2d(0.9, 0.9)];
+        let node_neighbors = vec![vec![1], vec![]];
+        let first_line = |edges_on_top, prefix| {
+            let canvas = SvgCanvas { edges_on_top, ..SvgCanvas::default_for_unit_layout() };
+            let svg = render(canvas, |wr| {
+                wr.draw_graph(&node_positions, &node_neighbors, EdgeMode::AsStored)
+            });
+            svg.lines().position(|line| line.starts_with(prefix)).unwrap()
+        };
+        assert!(first_line(false, "<path") < first_line(false, "<circle"));
+        assert!(first_line(true, "<path") > first_line(true, "<circle"));
+    }
+
+    #[test]
+    fn inline_mode_omits_the_xml_declaration() {
+        let draw = |canvas| {
+            render(canvas, |wr| wr.draw_graph(&[P2d(0.5, 0.5)], &[vec![]], EdgeMode::AsStored))
+        };
+        assert!(draw(SvgCanvas::default_for_unit_layout()).starts_with("<?xml"));
+        let canvas = SvgCanvas {
+            inline: true,
+            view_box: Some((0.0, 0.0, 50.0, 50.0)),
+            ..SvgCanvas::default_for_unit_layout()
+        };
+        let svg = draw(canvas);
+        assert!(!svg.contains("<?xml"));
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("viewBox=\"0 0 50 50\""));
+    }
+
+    #[test]
+    fn tooltip_is_a_title_within_the_circle() {
+        let node_positions = [P2d(0.2, 0.2), P2d(0.8, 0.8)];
+        let tooltips = ["hello".to_string()];
+        let mut out = Vec::new();
+        SvgWriter::new(SvgCanvas::default_for_unit_layout(), &mut out)
+            .tooltips(&tooltips)
+            .draw_graph(&node_positions, &[vec![], vec![]], EdgeMode::AsStored)
+            .unwrap();
+        let svg = String::from_utf8(out).unwrap();
+        let nodes: Vec<&str> = svg.lines().filter(|line| line.starts_with("<circle")).collect();
+        assert_eq!(nodes.len(), 2);
+        assert!(nodes[0].contains("><title>hello</title></circle>"));
+        assert!(!nodes[1].contains("<title>"));
+    }
+
+    #[test]
+    fn linked_node_is_wrapped_in_an_anchor() {
+        let node_positions = [P2d(0.2, 0.2), P2d(0.8, 0.8)];
+        let links = [Some("https://example.org/?a=1&b=2".to_string()), None];
+        let mut out = Vec::new();
+        SvgWriter::new(SvgCanvas::default_for_unit_layout(), &mut out)
+            .links(&links)
+            .draw_graph(&node_positions, &[vec![], vec![]], EdgeMode::AsStored)
+            .unwrap();
+        let svg = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = svg.lines().collect();
+        let first = lines.iter().position(|line| line.starts_with("<circle")).unwrap();
+        assert_eq!(lines[first - 1], r#"<a href="https://example.org/?a=1&amp;b=2">"#);
+        assert_eq!(lines[first + 1], "</a>");
+        assert!(lines[first + 2].starts_with("<circle"));
+        assert_eq!(svg.matches("<a ").count(), 1);
+    }
+
+    #[test]
+    fn median_edge_renders_at_target_px() {
+        let node_positions = [P2d(0.0, 0.0), P2d(0.1, 0.0), P2d(0.1, 0.3), P2d(0.5, 0.3)];
+        let node_neighbors = vec![vec![1], vec![2], vec![3], vec![]];
+        let canvas = SvgCanvas::for_target_edge_px(&node_positions, &node_neighbors, 60.0);
+        let mut lengths: Vec<f32> = adjacency::edges(&node_neighbors)
+                                        .map(|(src, dst)| {
+                                            let a = canvas.to_screen(&node_positions[src]);
+                                            let b = canvas.to_screen(&node_positions[dst]);
+                                            ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+                                        })
+                                        .collect();
+        lengths.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert!((lengths[1] - 60.0).abs() < 0.5, "median edge is {}px", lengths[1]);
+        assert!((canvas.width - 0.5 * 200.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn animation_has_one_animated_circle_per_node() {
+        let frames = vec![vec![P2d(0.1, 0.1), P2d(0.9, 0.1), P2d(0.5, 0.9)],
+                          vec![P2d(0.2, 0.2), P2d(0.8, 0.2), P2d(0.5, 0.8)],
+                          vec![P2d(0.3, 0.3), P2d(0.7, 0.3), P2d(0.5, 0.7)]];
+        let node_neighbors = vec![vec![1], vec![2], vec![0]];
+        let svg = render(SvgCanvas::default_for_unit_layout(),
+                         |wr| wr.draw_animated_graph(&frames, &node_neighbors, 10.0));
+        assert_eq!(svg.matches("<circle").count(), 3);
+        assert_eq!(svg.matches("<animate attributeName=\"cx\"").count(), 3);
+        assert_eq!(svg.matches("<animate attributeName=\"cy\"").count(), 3);
+        let values = svg.lines()
+                        .find(|line| line.contains("attributeName=\"cx\""))
+                        .unwrap()
+                        .split("values=\"")
+                        .nth(1)
+                        .unwrap();
+        assert_eq!(values.split('"').next().unwrap().split(';').count(), frames.len());
+    }
+
+    #[test]
+    fn nodes_and_edges_are_grouped_once() {
+        let node_positions = [P2d(0.1, 0.1), P2d(0.9, 0.1), P2d(0.5, 0.9)];
+        let node_neighbors = vec![vec![1], vec![2], vec![0]];
+        let node_classes = vec!["a".to_string(), String::new(), "c".to_string()];
+        let mut out = Vec::new();
+        SvgWriter::new(SvgCanvas::default_for_unit_layout(), &mut out)
+            .node_classes(&node_classes)
+            .draw_graph(&node_positions, &node_neighbors, EdgeMode::AsStored)
+            .unwrap();
+        let svg = String::from_utf8(out).unwrap();
+        assert_eq!(svg.matches(r#"<g class="nodes">"#).count(), 1);
+        assert_eq!(svg.matches(r#"<g class="edges">"#).count(), 1);
+        assert_eq!(svg.matches("<g").count(), svg.matches("</g>").count());
+        assert!(svg.contains(r#"class="a""#) && svg.contains(r#"class="c""#));
+        assert_eq!(svg.matches(" class=\"").count(), 4);
+    }
+
+    #[test]
+    fn edge_modes_draw_a_symmetric_triangle() {
+        let node_positions = [P2d(0.1, 0.1), P2d(0.9, 0.1), P2d(0.5, 0.9)];
+        let node_neighbors = vec![vec![1, 2], vec![0, 2], vec![0, 1]];
+        let draw = |mode| {
+            render(SvgCanvas::default_for_unit_layout(),
+                   |wr| wr.draw_graph(&node_positions, &node_neighbors, mode))
+        };
+
+        let svg = draw(EdgeMode::AsStored);
+        assert_eq!(paths(&svg).len(), 6);
+        assert!(!svg.contains("marker"));
+
+        let svg = draw(EdgeMode::UndirectedDedup);
+        assert_eq!(paths(&svg).len(), 3);
+        assert!(!svg.contains("marker"));
+
+        let svg = draw(EdgeMode::Directed);
+        let paths = paths(&svg);
+        assert_eq!(paths.len(), 6);
+        assert!(paths.iter().all(|path| path.contains("url(#arrow)")));
+    }
+
+    #[test]
+    fn horizontal_edge_is_arced_when_configured() {
+        let node_positions = [P2d(0.1, 0.5), P2d(0.9, 0.5), P2d(0.5, 0.9)];
+        let node_neighbors = vec![vec![1, 2], vec![], vec![]];
+        let draw = |canvas| {
+            render(canvas, |wr| wr.draw_graph(&node_positions, &node_neighbors, EdgeMode::AsStored))
+        };
+
+        let straight = draw(SvgCanvas::default_for_unit_layout());
+        assert!(paths(&straight).iter().all(|path| !path.contains(" Q")));
+
+        let arced = draw(SvgCanvas {
+            arc_horizontal_edges: Some(0.2),
+            ..SvgCanvas::default_for_unit_layout()
+        });
+        let paths = paths(&arced);
+        assert_eq!(paths.len(), 2);
+        // only the horizontal edge, drawn first, is curved.
+        assert!(paths[0].contains(" Q"));
+        assert!(!paths[1].contains(" Q"));
+    }
+
+    #[test]
+    fn each_node_with_force_gets_an_arrow() {
+        let node_positions = [P2d(0.1, 0.1), P2d(0.9, 0.1), P2d(0.5, 0.9), P2d(0.5, 0.5)];
+        let forces = [P2d(1.0, 0.0), P2d(0.0, -2.0), P2d(-0.5, 0.5), P2d(0.0, 0.0)];
+        let svg = render(SvgCanvas::default_for_unit_layout(),
+                         |mut wr| Ok(wr.force_arrows(&node_positions, &forces, 0.1)?));
+        assert_eq!(svg.matches(r#"<path class="force""#).count(), 3);
+        assert_eq!(svg.matches("marker-end=\"url(#arrow)\"").count(), 3);
+    }
+
+    #[test]
+    fn si_format_rounds_to_three_significant_digits() {
+        assert_eq!(format_si(1234.5), "1.23k");
+        assert_eq!(format_si(0.05), "50.0m");
+        assert_eq!(format_si(-2.5e6), "-2.50M");
+        assert_eq!(format_si(999.9), "1.00k");
+        assert_eq!(format_si(99.996), "100");
+        assert_eq!(format_si(9.999), "10.0");
+        assert_eq!(format_si(0.0), "0");
+    }
+
+    #[test]
+    fn weight_label_is_formatted() {
+        let node_positions = [P2d(0.1, 0.1), P2d(0.9, 0.1)];
+        let node_neighbors = vec![vec![1], vec![]];
+        let svg = render(SvgCanvas::default_for_unit_layout(), |wr| {
+            wr.draw_graph_weighted_labeled(&node_positions,
+                                           &node_neighbors,
+                                           EdgeMode::AsStored,
+                                           &[1234.5],
+                                           format_si)
+        });
+        assert!(svg.contains(">1.23k</text>"));
+    }
+
+    #[test]
+    fn halo_draws_a_larger_circle_behind_each_node() {
+        let node_positions = [P2d(0.2, 0.3), P2d(0.7, 0.8)];
+        let canvas = SvgCanvas {
+            halo: Some((3.0, "yellow".to_string())),
+            ..SvgCanvas::default_for_unit_layout()
+        };
+        let radius = canvas.radius;
+        let node_neighbors = [vec![], vec![]];
+        let svg = render(canvas,
+                         |wr| wr.draw_graph(&node_positions, &node_neighbors, EdgeMode::AsStored));
+        let circles: Vec<&str> = svg.lines().filter(|line| line.starts_with("<circle")).collect();
+        assert_eq!(circles.len(), 4);
+        for pair in circles.chunks(2) {
+            let (halo, node) = (pair[0], pair[1]);
+            assert!(halo.contains("fill=\"yellow\""));
+            assert_eq!((attr(halo, "cx"), attr(halo, "cy")), (attr(node, "cx"), attr(node, "cy")));
+            assert_eq!(attr(halo, "r"), radius + 3.0);
+            assert_eq!(attr(node, "r"), radius);
+        }
+    }
+
+    #[test]
+    fn heavier_directed_edge_has_larger_arrow() {
+        let node_positions = [P2d(0.1, 0.1), P2d(0.9, 0.1), P2d(0.5, 0.9)];
+        let node_neighbors = vec![vec![1], vec![2], vec![]];
+        let svg = render(SvgCanvas::default_for_unit_layout(), |wr| {
+            wr.draw_graph_weighted(&node_positions,
+                                   &node_neighbors,
+                                   EdgeMode::Directed,
+                                   &[1.0, 4.0])
+        });
+        // the marker is sized in units of the stroke width of its edge.
+        assert!(svg.contains(r#"markerUnits="strokeWidth""#));
+        let paths = paths(&svg);
+        assert_eq!(paths.len(), 2);
+        assert!(paths.iter().all(|path| path.contains("url(#arrow)")));
+        let (light, heavy) = (attr(paths[0], "stroke-width"), attr(paths[1], "stroke-width"));
+        assert!((heavy - 4.0 * light).abs() < 1e-4, "widths {} and {}", light, heavy);
+    }
+
+    #[test]
+    fn marker_is_only_defined_for_directed_edges() {
+        let node_positions = [P2d(0.1, 0.1), P2d(0.9, 0.1)];
+        let node_neighbors = vec![vec![1], vec![0]];
+        for &mode in [EdgeMode::AsStored, EdgeMode::UndirectedDedup].iter() {
+            let svg = render(SvgCanvas::default_for_unit_layout(),
+                             |wr| wr.draw_graph(&node_positions, &node_neighbors, mode));
+            assert!(!svg.contains("<marker"));
+            assert!(!svg.contains("<defs>"));
+        }
+        let svg = render(SvgCanvas::default_for_unit_layout(), |wr| {
+            wr.draw_graph(&node_positions, &node_neighbors, EdgeMode::Directed)
+        });
+        assert_eq!(svg.matches("<marker").count(), 1);
+    }
+
+    #[test]
+    fn streamed_output_equals_batch_output() {
+        let node_positions = [P2d(0.1, 0.1), P2d(0.9, 0.1), P2d(0.5, 0.9), P2d(0.4, 0.4)];
+        let node_neighbors = vec![vec![1, 2], vec![2], vec![0], vec![]];
+        for &edges_on_top in [false, true].iter() {
+            let canvas = SvgCanvas { edges_on_top, ..SvgCanvas::default_for_unit_layout() };
+            let batch = render(canvas.clone(), |wr| {
+                wr.draw_graph(&node_positions, &node_neighbors, EdgeMode::AsStored)
+            });
+            let streamed = render(canvas, |wr| {
+                wr.draw_graph_streamed(node_positions.iter().cloned(),
+                                       edges(&node_neighbors).map(|(src, dst)| {
+                                           (node_positions[src], node_positions[dst])
+                                       }))
+            });
+            assert_eq!(streamed, batch);
+        }
+    }
+
+    #[test]
+    fn to_screen_matches_the_drawn_node() {
+        let pos = P2d(0.3, 0.6);
+        for &flip_y in [false, true].iter() {
+            let canvas = SvgCanvas {
+                offsetx: 12.0,
+                offsety: -7.0,
+                scaley: 500.0,
+                flip_y,
+                ..SvgCanvas::default_for_unit_layout()
+            };
+            let screen = canvas.to_screen(&pos);
+            let svg = render(canvas, |wr| wr.draw_graph(&[pos], &[vec![]], EdgeMode::AsStored));
+            let circle = svg.lines().find(|line| line.starts_with("<circle")).unwrap();
+            assert_eq!((attr(circle, "cx"), attr(circle, "cy")), (screen.0, screen.1));
+        }
+    }
+
+    #[test]
+    fn to_world_inverts_to_screen() {
+        let canvas = SvgCanvas {
+            offsetx: 12.0,
+            offsety: -7.0,
+            scaley: 500.0,
+            flip_y: true,
+            ..SvgCanvas::default_for_unit_layout()
+        };
+        for &pos in [P2d(0.0, 0.0), P2d(0.3, 0.6), P2d(1.0, 1.0), P2d(-0.2, 1.5)].iter() {
+            let world = canvas.to_world(&canvas.to_screen(&pos));
+            assert!((world.0 - pos.0).abs() < 1e-5 && (world.1 - pos.1).abs() < 1e-5);
+        }
+
+        // a collapsed axis maps to zero.
+        let flat = SvgCanvas { scaley: 0.0, ..SvgCanvas::default_for_unit_layout() };
+        let world = flat.to_world(&flat.to_screen(&P2d(0.4, 0.8)));
+        assert!((world.0 - 0.4).abs() < 1e-5);
+        assert_eq!(world.1, 0.0);
+    }
+
+    #[test]
+    fn title_is_centered_in_the_top_margin() {
+        let node_positions = [P2d(0.0, 0.0), P2d(1.0, 1.0)];
+        let node_neighbors = vec![vec![1], vec![]];
+        let canvas = SvgCanvas::default_for_unit_layout();
+        let full_width = canvas.width + 2.0 * canvas.border;
+        let mut out = Vec::new();
+        SvgWriter::new(canvas, &mut out)
+            .title("Before & after", 20.0)
+            .draw_graph(&node_positions, &node_neighbors, EdgeMode::AsStored)
+            .unwrap();
+        let svg = String::from_utf8(out).unwrap();
 
-        self.footer();
+        let view_box = format!(r#"viewBox="0 -40 {} {}""#, full_width, full_width + 40.0);
+        assert!(svg.contains(&view_box));
+        let text = svg.lines().find(|line| line.starts_with("<text")).unwrap();
+        assert!(text.ends_with(">Before &amp; after</text>"));
+        assert_eq!(attr(text, "x"), 0.5 * full_width);
+        // within the margin above the drawing, which starts at y = 0.
+        let y = attr(text, "y");
+        assert!(y > -40.0 && y < 0.0);
+        let circle = svg.lines().find(|line| line.starts_with("<circle")).unwrap();
+        assert!(attr(circle, "cy") > y);
     }
 }
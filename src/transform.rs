@@ -0,0 +1,526 @@
+//!
+//! Transformations applied to a whole layout.
+//!
+
+use std::f32::consts::PI;
+use super::{P2d, Vector};
+use super::adjacency::undirected_edges;
+use super::geometry::bounding_box;
+use super::metrics::box_overlaps;
+
+/// Rounds all coordinates to the nearest multiple of `grid`, so that
+/// layouts differing only by floating point noise become identical.
+pub fn quantize_positions(node_positions: &mut [P2d], grid: f32) {
+    assert!(grid > 0.0);
+    for pos in node_positions.iter_mut() {
+        pos.0 = (pos.0 / grid).round() * grid;
+        pos.1 = (pos.1 / grid).round() * grid;
+    }
+}
+
+/// Moves all nodes by `offset`.
+pub fn translate_positions<V>(node_positions: &mut [V], offset: &V)
+    where V: Vector<Scalar = f32>
+{
+    for pos in node_positions.iter_mut() {
+        pos.add_scaled(1.0, offset);
+    }
+}
+
+/// Scales the layout uniformly and moves it, so that it fits into the unit
+/// square, touching its left and top (or bottom, with the y-axis pointing
+/// up) edges. Keeps the aspect ratio. A layout of a single point is moved
+/// to the origin.
+pub fn normalize_to_unit_box(node_positions: &mut [P2d]) {
+    if node_positions.is_empty() {
+        return;
+    }
+    let (min, max) = bounding_box(node_positions);
+    let extent = (max.0 - min.0).max(max.1 - min.1);
+    let scale = if extent > 0.0 { 1.0 / extent } else { 0.0 };
+    for pos in node_positions.iter_mut() {
+        *pos = P2d((pos.0 - min.0) * scale, (pos.1 - min.1) * scale);
+    }
+}
+
+/// Places two layouts next to each other, e.g. to compare a layout before
+/// and after a change in one figure. Normalizes both with
+/// `normalize_to_unit_box`, and moves the right one by `1 + gap` along the
+/// x-axis, so that at least `gap` separates them.
+pub fn compose_side_by_side(left: &[P2d], right: &[P2d], gap: f32) -> (Vec<P2d>, Vec<P2d>) {
+    let mut left = left.to_vec();
+    let mut right = right.to_vec();
+    normalize_to_unit_box(&mut left);
+    normalize_to_unit_box(&mut right);
+    translate_positions(&mut right, &P2d(1.0 + gap, 0.0));
+    (left, right)
+}
+
+/// Rotates the layout around its centroid, so that its principal axis,
+/// i.e. the direction of largest variance of the positions, is
+/// horizontal. This makes layouts, which only differ by rotation,
+/// comparable.
+pub fn align_principal_axis(node_positions: &mut [P2d]) {
+    if node_positions.is_empty() {
+        return;
+    }
+    let n = node_positions.len() as f32;
+    let cx = node_positions.iter().map(|p| p.0).sum::<f32>() / n;
+    let cy = node_positions.iter().map(|p| p.1).sum::<f32>() / n;
+
+    // covariance matrix [[sxx, sxy], [sxy, syy]]
+    let (mut sxx, mut sxy, mut syy) = (0.0, 0.0, 0.0);
+    for p in node_positions.iter() {
+        let (dx, dy) = (p.0 - cx, p.1 - cy);
+        sxx += dx * dx;
+        sxy += dx * dy;
+        syy += dy * dy;
+    }
+
+    // angle of the eigenvector of the larger eigenvalue.
+    let angle = 0.5 * (2.0 * sxy).atan2(sxx - syy);
+    let (sin, cos) = (-angle).sin_cos();
+    for p in node_positions.iter_mut() {
+        let (dx, dy) = (p.0 - cx, p.1 - cy);
+        *p = P2d(cx + dx * cos - dy * sin, cy + dx * sin + dy * cos);
+    }
+}
+
+/// Pulls connected nodes closer together, to make the layout more
+/// compact. Performs `iterations` sweeps of gradient descent on the total
+/// edge length plus a repulsion barrier, moving one node at a time. The
+/// barrier grows quadratically as two nodes come closer than `2 * min_sep`,
+/// which keeps nodes from piling up against each other. The step starts at
+/// half the mean edge length and cools down linearly. A move is only taken
+/// if it lowers this objective for the node, and brings it no closer than
+/// `min_sep` to any other node (unless it is moving away from it). Edges
+/// count once, no matter if they are stored in one or both directions.
+pub fn minimize_edge_length(node_positions: &mut [P2d],
+                            node_neighbors: &[Vec<usize>],
+                            min_sep: f32,
+                            iterations: usize) {
+    assert!(min_sep >= 0.0);
+    let n = node_positions.len();
+    let mut incident = vec![Vec::new(); n];
+    for (src, dst) in undirected_edges(node_neighbors).filter(|&(src, dst)| src != dst) {
+        incident[src].push(dst);
+        incident[dst].push(src);
+    }
+    let num_edges = incident.iter().map(|nb| nb.len()).sum::<usize>() / 2;
+    if num_edges == 0 {
+        return;
+    }
+    // each edge is counted from both ends.
+    let total_length: f32 = (0..n)
+        .flat_map(|i| incident[i].iter().map(move |&j| (i, j)))
+        .map(|(i, j)| node_positions[i].distance_squared_to_point(&node_positions[j]).sqrt())
+        .sum();
+    let initial_step = 0.5 * total_length / (2 * num_edges) as f32;
+
+    let min_sep_squared = min_sep * min_sep;
+    let barrier = 2.0 * min_sep;
+    // the edge length of node `i` at `pos`, plus its barrier.
+    let objective_at = |positions: &[P2d], i: usize, pos: &P2d| -> f32 {
+        let length: f32 =
+            incident[i].iter().map(|&j| pos.distance_squared_to_point(&positions[j]).sqrt()).sum();
+        if min_sep == 0.0 {
+            return length;
+        }
+        let penalty: f32 = (0..n)
+            .filter(|&j| j != i)
+            .map(|j| (barrier - pos.distance_squared_to_point(&positions[j]).sqrt()).max(0.0))
+            .map(|overlap| overlap * overlap / min_sep)
+            .sum();
+        length + penalty
+    };
+
+    for iter in 0..iterations {
+        let step = initial_step * (iterations - iter) as f32 / iterations as f32;
+        for i in 0..n {
+            let pos = node_positions[i];
+            let mut gradient = P2d(0.0, 0.0);
+            for &j in incident[i].iter() {
+                let d = pos.sub(&node_positions[j]);
+                let length = d.length_squared().sqrt();
+                if length > 0.0 {
+                    gradient.add_scaled(1.0 / length, &d);
+                }
+            }
+            for j in (0..n).filter(|&j| j != i) {
+                let d = pos.sub(&node_positions[j]);
+                let length = d.length_squared().sqrt();
+                if length > 0.0 && length < barrier {
+                    gradient.add_scaled(-2.0 * (barrier - length) / (min_sep * length), &d);
+                }
+            }
+            let length = gradient.length_squared().sqrt();
+            if length == 0.0 {
+                continue;
+            }
+            let mut new_pos = pos;
+            new_pos.add_scaled(-step / length, &gradient);
+
+            let separated = (0..n).filter(|&j| j != i).all(|j| {
+                let d = new_pos.distance_squared_to_point(&node_positions[j]);
+                d >= min_sep_squared || d >= pos.distance_squared_to_point(&node_positions[j])
+            });
+            if separated &&
+               objective_at(node_positions, i, &new_pos) < objective_at(node_positions, i, &pos) {
+                node_positions[i] = new_pos;
+            }
+        }
+    }
+}
+
+// Angles between edges may fall short of the minimum by this much (in
+// radians), so that rounding does not count as a violation.
+const ANGLE_TOLERANCE: f32 = 1e-4;
+
+/// Rotates edges around their nodes, so that the angle between any two
+/// edges meeting at a node is at least `min_angle` (in radians), or
+/// `2 * PI / degree` if the node has too many edges for `min_angle`. The
+/// far end of each edge is moved on the circle around the node, shorter
+/// edges moving further than longer ones.
+///
+/// Moving an endpoint changes the angles at its own node, so the nodes are
+/// swept by increasing degree, hubs last, until the constraint holds at
+/// all nodes, at most `max_sweeps` times. Returns whether it holds.
+/// Self-loops and edges of zero length are ignored.
+pub fn enforce_min_edge_angle(node_positions: &mut [P2d],
+                              node_neighbors: &[Vec<usize>],
+                              min_angle: f32,
+                              max_sweeps: usize)
+                              -> bool {
+    assert!(min_angle >= 0.0);
+    let n = node_positions.len();
+    let mut incident = vec![Vec::new(); n];
+    for (src, dst) in undirected_edges(node_neighbors).filter(|&(src, dst)| src != dst) {
+        incident[src].push(dst);
+        incident[dst].push(src);
+    }
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by_key(|&i| incident[i].len());
+    for _ in 0..max_sweeps {
+        let mut moved = false;
+        for &i in order.iter() {
+            moved |= spread_edges(node_positions, i, &incident[i], min_angle);
+        }
+        if !moved {
+            return true;
+        }
+    }
+    false
+}
+
+// Spreads the edges from `center` to `neighbors` apart, as described in
+// `enforce_min_edge_angle`. Returns whether they were too close.
+fn spread_edges(node_positions: &mut [P2d],
+                center: usize,
+                neighbors: &[usize],
+                min_angle: f32)
+                -> bool {
+    let c = node_positions[center];
+    // (angle, length, node) of each edge.
+    let mut spokes: Vec<(f32, f32, usize)> = neighbors.iter()
+        .filter_map(|&j| {
+            let d = node_positions[j].sub(&c);
+            let length = d.length_squared().sqrt();
+            if length > 0.0 { Some((d.1.atan2(d.0), length, j)) } else { None }
+        })
+        .collect();
+    let d = spokes.len();
+    if d < 2 {
+        return false;
+    }
+    let gap = min_angle.min(2.0 * PI / d as f32);
+    spokes.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    let gap_after = |k: usize| if k + 1 < d {
+        spokes[k + 1].0 - spokes[k].0
+    } else {
+        spokes[0].0 + 2.0 * PI - spokes[d - 1].0
+    };
+    if (0..d).all(|k| gap_after(k) >= gap - ANGLE_TOLERANCE) {
+        return false;
+    }
+
+    // cut the circle at the widest gap, and unwrap the angles from there.
+    let widest = (0..d).max_by(|&a, &b| gap_after(a).partial_cmp(&gap_after(b)).unwrap()).unwrap();
+    spokes.rotate_left((widest + 1) % d);
+    for k in 1..d {
+        if spokes[k].0 < spokes[k - 1].0 {
+            spokes[k].0 += 2.0 * PI;
+        }
+    }
+
+    // consecutive angles are at least `gap` apart, iff `angle_k - k * gap`
+    // is non-decreasing. the closest such angles, weighted by length, are
+    // found by isotonic regression.
+    let shifted: Vec<f32> = spokes.iter().enumerate().map(|(k, s)| s.0 - k as f32 * gap).collect();
+    let weights: Vec<f32> = spokes.iter().map(|s| s.1).collect();
+    let mut angles: Vec<f32> = isotonic_regression(&shifted, &weights)
+        .iter()
+        .enumerate()
+        .map(|(k, a)| a + k as f32 * gap)
+        .collect();
+    if angles[d - 1] - angles[0] > 2.0 * PI - gap {
+        // the gap across the cut got too small; space evenly instead.
+        let step = 2.0 * PI / d as f32;
+        let start = spokes.iter().enumerate().map(|(k, s)| s.0 - k as f32 * step).sum::<f32>() /
+                    d as f32;
+        angles = (0..d).map(|k| start + k as f32 * step).collect();
+    }
+
+    for (&(_, length, j), &angle) in spokes.iter().zip(angles.iter()) {
+        let (sin, cos) = angle.sin_cos();
+        node_positions[j] = P2d(c.0 + length * cos, c.1 + length * sin);
+    }
+    true
+}
+
+/// The non-decreasing sequence closest to `values` in the weighted least
+/// squares sense, by the pool adjacent violators algorithm.
+pub(crate) fn isotonic_regression(values: &[f32], weights: &[f32]) -> Vec<f32> {
+    // (weighted mean, total weight, count) of each pool.
+    let mut pools: Vec<(f32, f32, usize)> = Vec::with_capacity(values.len());
+    for (&value, &weight) in values.iter().zip(weights.iter()) {
+        let mut pool = (value, weight, 1);
+        while let Some(&(mean, w, count)) = pools.last() {
+            if mean <= pool.0 {
+                break;
+            }
+            pools.pop();
+            let total = w + pool.1;
+            pool = ((mean * w + pool.0 * pool.1) / total, total, count + pool.2);
+        }
+        pools.push(pool);
+    }
+    pools.iter().flat_map(|&(mean, _, count)| (0..count).map(move |_| mean)).collect()
+}
+
+// Overlapping nodes are pushed apart until this fraction of their summed
+// half sizes lies between them, so that rounding does not leave them
+// overlapping.
+const SEPARATION_GAP: f32 = 0.01;
+
+/// Pushes overlapping nodes apart, treating each node as an axis-aligned
+/// box with the half-width and half-height given in `half_sizes`. Covers
+/// circles and ellipses by their bounding boxes, e.g. nodes with wide
+/// labels. Each pair of overlapping boxes is separated along the axis it
+/// overlaps less in, which moves the nodes the least. Sweeps over all pairs
+/// until no boxes overlap, at most `max_sweeps` times, and returns whether
+/// all overlaps were removed. Takes quadratic time per sweep.
+pub fn remove_overlaps(node_positions: &mut [P2d], half_sizes: &[P2d], max_sweeps: usize) -> bool {
+    assert_eq!(node_positions.len(), half_sizes.len());
+    let n = node_positions.len();
+    for _ in 0..max_sweeps {
+        let mut overlapping = false;
+        for i in 0..n {
+            for j in i + 1..n {
+                let (dx, dy) = (node_positions[j].0 - node_positions[i].0,
+                                node_positions[j].1 - node_positions[i].1);
+                let overlap_x = half_sizes[i].0 + half_sizes[j].0 - dx.abs();
+                let overlap_y = half_sizes[i].1 + half_sizes[j].1 - dy.abs();
+                if overlap_x <= 0.0 || overlap_y <= 0.0 {
+                    continue;
+                }
+                overlapping = true;
+                // nodes at the same coordinate are pushed apart by index.
+                let push = |d: f32, overlap: f32, size: f32| {
+                    let shift = 0.5 * (overlap + SEPARATION_GAP * size);
+                    if d < 0.0 { -shift } else { shift }
+                };
+                if overlap_x <= overlap_y {
+                    let shift = push(dx, overlap_x, half_sizes[i].0 + half_sizes[j].0);
+                    node_positions[i].0 -= shift;
+                    node_positions[j].0 += shift;
+                } else {
+                    let shift = push(dy, overlap_y, half_sizes[i].1 + half_sizes[j].1);
+                    node_positions[i].1 -= shift;
+                    node_positions[j].1 += shift;
+                }
+            }
+        }
+        if !overlapping {
+            return true;
+        }
+    }
+    box_overlaps(node_positions, half_sizes) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use adjacency::edges;
+
+    #[test]
+    fn quantizing_makes_nearly_equal_layouts_identical() {
+        let mut a = vec![P2d(0.123_41, 0.5), P2d(0.999_98, 0.25)];
+        let mut b = vec![P2d(0.123_39, 0.500_01), P2d(1.000_01, 0.249_99)];
+        quantize_positions(&mut a, 1e-3);
+        quantize_positions(&mut b, 1e-3);
+        for (p, q) in a.iter().zip(b.iter()) {
+            assert_eq!((p.0.to_bits(), p.1.to_bits()), (q.0.to_bits(), q.1.to_bits()));
+        }
+    }
+
+    #[test]
+    fn translate_shifts_all_points() {
+        let mut node_positions = vec![P2d(0.0, 0.0), P2d(1.0, -2.0)];
+        translate_positions(&mut node_positions, &P2d(0.5, 3.0));
+        assert_eq!((node_positions[0].0, node_positions[0].1), (0.5, 3.0));
+        assert_eq!((node_positions[1].0, node_positions[1].1), (1.5, 1.0));
+    }
+
+    #[test]
+    fn aligned_elongated_cloud_is_wider_than_tall() {
+        // points along a diagonal, slightly spread across it.
+        let mut node_positions: Vec<P2d> = (0..20)
+            .map(|i| {
+                let t = i as f32 / 19.0;
+                let spread = if i % 2 == 0 { 0.05 } else { -0.05 };
+                P2d(t + spread, t - spread)
+            })
+            .collect();
+        align_principal_axis(&mut node_positions);
+        let (min, max) = bounding_box(&node_positions);
+        assert!(max.0 - min.0 > 5.0 * (max.1 - min.1));
+    }
+
+    fn total_edge_length(node_positions: &[P2d], node_neighbors: &[Vec<usize>]) -> f32 {
+        edges(node_neighbors)
+            .map(|(src, dst)| node_positions[src].distance_squared_to_point(&node_positions[dst]))
+            .map(f32::sqrt)
+            .sum()
+    }
+
+    fn min_distance(node_positions: &[P2d]) -> f32 {
+        let mut min = f32::INFINITY;
+        for (i, p) in node_positions.iter().enumerate() {
+            for q in node_positions[i + 1..].iter() {
+                min = min.min(p.distance_squared_to_point(q).sqrt());
+            }
+        }
+        min
+    }
+
+    #[test]
+    fn minimizing_shortens_edges_and_keeps_separation() {
+        // a ring of 8 nodes, stretched over a wide circle.
+        let node_neighbors: Vec<Vec<usize>> = (0..8).map(|i| vec![(i + 1) % 8]).collect();
+        let initial: Vec<P2d> = (0..8)
+            .map(|i| {
+                let angle = 2.0 * PI * i as f32 / 8.0;
+                P2d(0.5 + 0.4 * angle.cos(), 0.5 + 0.4 * angle.sin())
+            })
+            .collect();
+        let min_sep = 0.1;
+        let mut node_positions = initial.clone();
+        minimize_edge_length(&mut node_positions, &node_neighbors, min_sep, 50);
+        let before = total_edge_length(&initial, &node_neighbors);
+        let after = total_edge_length(&node_positions, &node_neighbors);
+        assert!(after < 0.8 * before, "edge length {} -> {}", before, after);
+        assert!(min_distance(&node_positions) >= min_sep - 1e-5);
+    }
+
+    #[test]
+    fn minimizing_without_separation_still_moves() {
+        let node_neighbors = vec![vec![1], vec![]];
+        let mut node_positions = vec![P2d(0.0, 0.0), P2d(1.0, 0.0)];
+        minimize_edge_length(&mut node_positions, &node_neighbors, 0.0, 10);
+        assert!(total_edge_length(&node_positions, &node_neighbors) < 0.5);
+    }
+
+    #[test]
+    fn minimizing_counts_edges_stored_both_ways_once() {
+        let one_way: Vec<Vec<usize>> = (0..6).map(|i| vec![(i + 1) % 6]).collect();
+        let both_ways: Vec<Vec<usize>> = (0..6).map(|i| vec![(i + 1) % 6, (i + 5) % 6]).collect();
+        let initial: Vec<P2d> = (0..6)
+            .map(|i| {
+                let angle = 2.0 * PI * i as f32 / 6.0;
+                P2d(0.5 + 0.4 * angle.cos(), 0.5 + 0.4 * angle.sin())
+            })
+            .collect();
+        let (mut a, mut b) = (initial.clone(), initial);
+        minimize_edge_length(&mut a, &one_way, 0.1, 20);
+        minimize_edge_length(&mut b, &both_ways, 0.1, 20);
+        // equal up to the order in which the edges of a node are summed.
+        for (p, q) in a.iter().zip(b.iter()) {
+            assert!(p.distance_squared_to_point(q).sqrt() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn composed_layouts_are_separated_by_the_gap() {
+        let left = [P2d(-3.0, 1.0), P2d(5.0, 2.0), P2d(0.0, -1.0)];
+        let right = [P2d(10.0, 10.0), P2d(10.5, 12.0)];
+        let gap = 0.25;
+        let (left, right) = compose_side_by_side(&left, &right, gap);
+        let (left_min, left_max) = bounding_box(&left);
+        let (right_min, right_max) = bounding_box(&right);
+        assert_eq!((left_min.0, left_min.1, left_max.0), (0.0, 0.0, 1.0));
+        assert_eq!((right_min.0, right_max.1), (1.0 + gap, 1.0));
+        assert!(right_min.0 - left_max.0 >= gap);
+    }
+
+    // The smallest angle between two edges meeting at a node, over all nodes.
+    fn smallest_edge_angle(node_positions: &[P2d], node_neighbors: &[Vec<usize>]) -> f32 {
+        let mut incident = vec![Vec::new(); node_positions.len()];
+        for (src, dst) in undirected_edges(node_neighbors) {
+            incident[src].push(dst);
+            incident[dst].push(src);
+        }
+        let mut smallest = PI;
+        for (i, neighbors) in incident.iter().enumerate() {
+            let mut angles: Vec<f32> = neighbors.iter()
+                .map(|&j| {
+                    let d = node_positions[j].sub(&node_positions[i]);
+                    d.1.atan2(d.0)
+                })
+                .collect();
+            angles.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            for k in 1..angles.len() {
+                smallest = smallest.min(angles[k] - angles[k - 1]);
+            }
+            if angles.len() > 1 {
+                smallest = smallest.min(angles[0] + 2.0 * PI - angles[angles.len() - 1]);
+            }
+        }
+        smallest
+    }
+
+    #[test]
+    fn min_edge_angle_holds_at_all_nodes() {
+        // a ternary tree of 16 nodes at scattered positions.
+        let n = 16;
+        let mut node_neighbors = vec![Vec::new(); n];
+        for i in 1..n {
+            node_neighbors[(i - 1) / 3].push(i);
+        }
+        let mut node_positions: Vec<P2d> = (0..n)
+            .map(|i| {
+                let t = i as f32 * 1.7;
+                P2d(0.5 + 0.4 * t.sin(), 0.5 + 0.4 * (2.3 * t).cos())
+            })
+            .collect();
+        let min_angle = PI / 6.0;
+        assert!(smallest_edge_angle(&node_positions, &node_neighbors) < min_angle);
+
+        assert!(enforce_min_edge_angle(&mut node_positions, &node_neighbors, min_angle, 100));
+        let smallest = smallest_edge_angle(&node_positions, &node_neighbors);
+        assert!(smallest >= min_angle - 1e-3, "smallest angle {}", smallest);
+    }
+
+    #[test]
+    fn wide_boxes_no_longer_overlap() {
+        // labels, much wider than high, stacked in a cluster.
+        let mut node_positions: Vec<P2d> = (0..12)
+            .map(|i| P2d(0.5 + 0.02 * (i % 3) as f32, 0.5 + 0.01 * (i / 3) as f32))
+            .collect();
+        let half_sizes = vec![P2d(0.1, 0.02); 12];
+        assert!(box_overlaps(&node_positions, &half_sizes) > 0);
+
+        assert!(remove_overlaps(&mut node_positions, &half_sizes, 100));
+        assert_eq!(box_overlaps(&node_positions, &half_sizes), 0);
+        assert!(node_positions.iter().all(|pos| pos.0.is_finite() && pos.1.is_finite()));
+    }
+}
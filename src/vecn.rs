@@ -0,0 +1,128 @@
+//!
+//! Points with a fixed number of dimensions, to run layouts beyond 2d.
+//!
+
+use super::vector::Vector;
+
+/// A point in `D` dimensions, e.g. `VecN<3>` for 3d layouts.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct VecN<const D: usize>(pub [f32; D]);
+
+impl<const D: usize> Vector for VecN<D> {
+    type Scalar = f32;
+
+    fn new() -> Self {
+        VecN([0.0; D])
+    }
+
+    fn length_squared(&self) -> f32 {
+        self.0.iter().map(|c| c * c).sum()
+    }
+
+    fn reset(&mut self) {
+        self.0 = [0.0; D];
+    }
+
+    fn scale(&self, factor: f32) -> Self {
+        let mut v = *self;
+        for c in v.0.iter_mut() {
+            *c *= factor;
+        }
+        v
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        let mut v = *self;
+        for (c, o) in v.0.iter_mut().zip(other.0.iter()) {
+            *c -= o;
+        }
+        v
+    }
+
+    fn add_scaled(&mut self, factor: f32, other: &Self) {
+        for (c, o) in self.0.iter_mut().zip(other.0.iter()) {
+            *c += factor * o;
+        }
+    }
+
+    // NaN components are replaced by the midpoint, as for `P2d`.
+    fn clip_within(&self, min: &Self, max: &Self) -> Self {
+        let mut v = *self;
+        for ((c, lo), hi) in v.0.iter_mut().zip(min.0.iter()).zip(max.0.iter()) {
+            *c = if c.is_nan() {
+                0.5 * (lo + hi)
+            } else if *c < *lo {
+                *lo
+            } else if *c > *hi {
+                *hi
+            } else {
+                *c
+            };
+        }
+        v
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use fruchterman_reingold::{layout, Layout};
+
+    #[test]
+    fn operations_in_two_and_three_dimensions() {
+        let v = VecN([3.0, 4.0]);
+        assert_eq!(v.length_squared(), 25.0);
+        assert_eq!(v.scale(2.0), VecN([6.0, 8.0]));
+        assert_eq!(v.sub(&VecN([1.0, 1.0])), VecN([2.0, 3.0]));
+        assert_eq!(VecN([-1.0, f32::NAN]).clip_within(&VecN([0.0, 0.0]), &VecN([1.0, 2.0])),
+                   VecN([0.0, 1.0]));
+
+        let v = VecN([1.0, 2.0, 2.0]);
+        assert_eq!(v.length_squared(), 9.0);
+        assert_eq!(v.scale(-1.0), VecN([-1.0, -2.0, -2.0]));
+        assert_eq!(v.sub(&VecN([1.0, 1.0, 1.0])), VecN([0.0, 1.0, 1.0]));
+        assert_eq!(VecN([2.0, 0.5, -3.0])
+                       .clip_within(&VecN([0.0, 0.0, 0.0]), &VecN([1.0, 1.0, 1.0])),
+                   VecN([1.0, 0.5, 0.0]));
+    }
+
+    // Lays out a triangle from `node_positions` with springs of length `l`
+    // and returns the lengths of its sides, shortest first.
+    fn triangle_sides<const D: usize>(mut node_positions: Vec<VecN<D>>, l: f32) -> Vec<f32> {
+        let node_neighbors = vec![vec![1], vec![2], vec![0]];
+        {
+            let mut lay = Layout::new(&mut node_positions, &node_neighbors).unwrap();
+            let _ = layout(&mut lay,
+                           |_| 0.01,
+                           200,
+                           0.0,
+                           l * l,
+                           l,
+                           &VecN([0.0; D]),
+                           &VecN([1.0; D]));
+        }
+        let mut lengths: Vec<f32> = (0..3)
+            .map(|i| {
+                node_positions[i].distance_squared_to_point(&node_positions[(i + 1) % 3]).sqrt()
+            })
+            .collect();
+        lengths.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        lengths
+    }
+
+    #[test]
+    fn layout_runs_in_two_and_three_dimensions() {
+        let l = 0.3;
+        let planar = triangle_sides(vec![VecN([0.1, 0.2]), VecN([0.4, 0.4]), VecN([0.5, 0.1])], l);
+        let spatial = triangle_sides(vec![VecN([0.1, 0.2, 0.3]),
+                                          VecN([0.4, 0.4, 0.4]),
+                                          VecN([0.5, 0.1, 0.9])],
+                                     l);
+        // equilateral triangles, whose sides are about `l` long.
+        for lengths in [planar, spatial] {
+            assert!(lengths[2] - lengths[0] < 0.02);
+            assert!((lengths[0] - l).abs() < 0.02);
+        }
+    }
+}
@@ -13,5 +13,34 @@ pub trait Vector: Clone {
 
     fn clip_within(&self, min: &Self, max: &Self) -> Self;
 
+    /// A vector to start from. All implementations in this crate return
+    /// the zero vector, but algorithms meaning the origin or "no force"
+    /// should say so by calling `zero`.
     fn new() -> Self;
+
+    /// The zero vector, i.e. the origin, or the absence of a force.
+    fn zero() -> Self {
+        Self::new()
+    }
+
+    /// The squared distance to `point`.
+    fn distance_squared_to_point(&self, point: &Self) -> Self::Scalar {
+        self.sub(point).length_squared()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use P2d;
+
+    #[test]
+    fn distance_squared_to_point_matches_manual_computation() {
+        let (p, q) = (P2d(1.5, -2.0), P2d(-0.5, 1.0));
+        let (dx, dy) = (p.0 - q.0, p.1 - q.1);
+        assert_eq!(p.distance_squared_to_point(&q), dx * dx + dy * dy);
+        assert_eq!(q.distance_squared_to_point(&p), 13.0);
+        assert_eq!(p.distance_squared_to_point(&p), 0.0);
+    }
 }